@@ -0,0 +1,221 @@
+//! Contains the futures behind the non-blocking request methods, built on tokio.
+
+use std::future::Future;
+use std::{cmp, io, vec};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::ReadBuf;
+use tokio::net::{self as tokio_net, UdpSocket};
+use tokio::time::{self, Sleep, Timeout};
+use types::*;
+use snmpv1;
+
+/// Resolves the address, sends the packet and waits for a reply, resending with a doubled
+/// timeout on each retry and waiting up to `jitter` before each send. Resolves to the reply as read by `parse`, or straight away to the error if the packet
+/// could not be built. Replies larger than `max_message_size` fail with `SnmpError::ResponseTooLarge`,
+/// and replies to other requests, which `parse` fails with `SnmpError::RequestIdMismatch`, are thrown away.
+pub(crate) fn exchange<T, P>(address: String, bind_addr: Option<SocketAddr>, sendpacket: Result<Vec<u8>, SnmpError>,
+                             policy: snmpv1::RetryPolicy, max_message_size: usize, parse: P) -> impl Future<Output = Result<T, SnmpError>>
+    where P: Fn(&[u8]) -> Result<T, SnmpError> {
+    let (sendpacket, error) = match sendpacket {
+        Ok(sendpacket) => (sendpacket, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    Exchange {
+        lookup: Box::pin(tokio_net::lookup_host(address)),
+        bind_addr: bind_addr,
+        error: error,
+        target: None,
+        sendpacket: Arc::new(sendpacket),
+        timeout: policy.timeout,
+        retries: policy.retries,
+        jitter: policy.jitter,
+        max_message_size: max_message_size,
+        parse: Arc::new(parse),
+        delay: None,
+        attempt: None,
+    }
+}
+
+struct Exchange<L, P> {
+    lookup: Pin<Box<L>>,
+    bind_addr: Option<SocketAddr>,
+    error: Option<SnmpError>,
+    target: Option<(Arc<UdpSocket>, SocketAddr)>,
+    sendpacket: Arc<Vec<u8>>,
+    timeout: u64,
+    retries: u32,
+    jitter: Option<Duration>,
+    max_message_size: usize,
+    parse: Arc<P>,
+    delay: Option<Pin<Box<Sleep>>>,
+    attempt: Option<Pin<Box<Timeout<Attempt<P>>>>>,
+}
+
+impl<L, I, P, T> Future for Exchange<L, P>
+    where L: Future<Output = io::Result<I>>, I: Iterator<Item = SocketAddr>, P: Fn(&[u8]) -> Result<T, SnmpError> {
+    type Output = Result<T, SnmpError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some(error) = this.error.take() {
+            return Poll::Ready(Err(error));
+        }
+
+        // Resolve the address and bind the UDP socket.
+        if this.target.is_none() {
+            let address = match this.lookup.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(addresses) => addresses?.next().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "the address did not resolve")
+                })?,
+            };
+            let socket = snmpv1::connect(this.bind_addr, &address)?;
+            socket.set_nonblocking(true)?;
+            this.target = Some((Arc::new(UdpSocket::from_std(socket)?), address));
+        }
+
+        loop {
+            if this.attempt.is_none() {
+                if let Some(jitter) = this.jitter {
+                    let delay = this.delay.get_or_insert_with(|| Box::pin(time::sleep(snmpv1::random_delay(jitter))));
+                    if delay.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    this.delay = None;
+                }
+                let (ref socket, address) = *this.target.as_ref().expect("the target is resolved");
+                let attempt = Attempt {
+                    socket: socket.clone(),
+                    address: address,
+                    sendpacket: this.sendpacket.clone(),
+                    receivepacket: snmpv1::receive_buffer(this.max_message_size),
+                    parse: this.parse.clone(),
+                    sent: false,
+                };
+                this.attempt = Some(Box::pin(time::timeout(Duration::from_millis(this.timeout), attempt)));
+            }
+
+            match this.attempt.as_mut().expect("an attempt is running").as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(reply)) => return Poll::Ready(reply),
+                Poll::Ready(Err(_)) => {
+                    if this.retries == 0 {
+                        return Poll::Ready(Err(SnmpError::Timeout));
+                    }
+                    this.retries -= 1;
+                    this.timeout = this.timeout.saturating_mul(2);
+                    this.attempt = None;
+                }
+            }
+        }
+    }
+}
+
+/// Sends the packet once and waits for a reply to it.
+struct Attempt<P> {
+    socket: Arc<UdpSocket>,
+    address: SocketAddr,
+    sendpacket: Arc<Vec<u8>>,
+    /// One byte more than the largest reply, to tell a reply that was cut off from one that fits.
+    receivepacket: Vec<u8>,
+    parse: Arc<P>,
+    sent: bool,
+}
+
+impl<P, T> Future for Attempt<P> where P: Fn(&[u8]) -> Result<T, SnmpError> {
+    type Output = Result<T, SnmpError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if !this.sent {
+            // The socket is connected, and the BSDs refuse `send_to` on a connected socket.
+            match this.socket.poll_send(cx, &this.sendpacket) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(sent) => { sent.map_err(snmpv1::classify)?; }
+            }
+            debug!("sent {} bytes to {}: {}", this.sendpacket.len(), this.address, Hex(&this.sendpacket));
+            this.sent = true;
+        }
+
+        loop {
+            let mut buf = ReadBuf::new(&mut this.receivepacket);
+            match this.socket.poll_recv_from(cx, &mut buf) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(received) => {
+                    received.map_err(snmpv1::classify)?;
+                    if buf.remaining() == 0 {
+                        return Poll::Ready(Err(SnmpError::ResponseTooLarge));
+                    }
+                    debug!("received {} bytes from {}: {}", buf.filled().len(), this.address, Hex(buf.filled()));
+                    match (this.parse)(buf.filled()) {
+                        Err(SnmpError::RequestIdMismatch) => debug!("discarding a reply to another request"),
+                        result => return Poll::Ready(result),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Runs the futures with no more than `limit` of them at a time, resolving to their outputs
+/// in the order the futures were given.
+pub(crate) fn join_limited<F: Future>(futures: Vec<F>, limit: usize) -> impl Future<Output = Vec<F::Output>> {
+    let outputs = futures.iter().map(|_| None).collect();
+    JoinLimited {
+        queued: futures.into_iter().map(Box::pin).enumerate().collect::<Vec<_>>().into_iter(),
+        running: Vec::new(),
+        outputs: outputs,
+        limit: cmp::max(limit, 1),
+    }
+}
+
+struct JoinLimited<F: Future> {
+    queued: vec::IntoIter<(usize, Pin<Box<F>>)>,
+    running: Vec<(usize, Pin<Box<F>>)>,
+    outputs: Vec<Option<F::Output>>,
+    limit: usize,
+}
+
+// The futures are boxed, so moving a JoinLimited is fine.
+impl<F: Future> Unpin for JoinLimited<F> {}
+
+impl<F: Future> Future for JoinLimited<F> {
+    type Output = Vec<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            // Start futures from the queue as others finish.
+            while this.running.len() < this.limit {
+                match this.queued.next() {
+                    Some(future) => this.running.push(future),
+                    None => break,
+                }
+            }
+            if this.running.is_empty() {
+                let outputs = this.outputs.drain(..).map(|output| output.expect("every future finished"));
+                return Poll::Ready(outputs.collect());
+            }
+
+            let mut finished = false;
+            let mut i = 0;
+            while i < this.running.len() {
+                match this.running[i].1.as_mut().poll(cx) {
+                    Poll::Pending => i += 1,
+                    Poll::Ready(output) => {
+                        let (index, _) = this.running.swap_remove(i);
+                        this.outputs[index] = Some(output);
+                        finished = true;
+                    },
+                }
+            }
+            if !finished {
+                return Poll::Pending;
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Contains a client that sends requests as whichever SNMP version its credentials are for.
+
+use std::sync::Mutex;
+use snmpv1::{self, Message};
+use snmpv3::{self, Authentication, Engine, Privacy};
+use types::*;
+
+/// The version of SNMP to speak to an agent, and what to authenticate with.
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    /// SNMPv1 with the given community.
+    V1 {
+        /// The community to authenticate with, which may be any bytes.
+        community: Vec<u8>,
+    },
+    /// SNMPv2c with the given community.
+    V2c {
+        /// The community to authenticate with, which may be any bytes.
+        community: Vec<u8>,
+    },
+    /// SNMPv3 as the given user, with authentication and privacy if configured.
+    V3 {
+        /// The user to send requests as.
+        username: String,
+        /// How to authenticate requests, if at all.
+        authentication: Option<Authentication>,
+        /// How to encrypt requests, if at all. Requires authentication.
+        privacy: Option<Privacy>,
+    },
+}
+
+/// A client for a single agent, which sends every request as the version of its credentials,
+/// so code talking to a mix of v1, v2c and v3 agents needs only the one type.
+///
+/// The engine of a SNMPv3 agent is discovered by the first request and reused by the ones after it.
+/// If the agent then reports the request out of its time window, the engine is discovered again
+/// and the request resent once.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::client::{Credentials, Snmp};
+///
+/// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+/// # let address = agent_socket.local_addr().unwrap().to_string();
+/// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+/// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+/// #     message
+/// # };
+/// # let agent = std::thread::spawn(move || {
+/// #     let mut buf = [0; 65535];
+/// #     let mut requests = Vec::new();
+/// #     for _ in 0..1 {
+/// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+/// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+/// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+/// #         requests.push(message);
+/// #     }
+/// #     requests
+/// # });
+///
+/// let snmp = Snmp::new(address, Credentials::V2c { community: b"public".to_vec() });
+/// let message = snmp.get(vec![1, 3, 6, 1, 2, 1, 1, 5, 0]).unwrap();
+/// assert_eq!(message.oid().arcs(), &[1, 3, 6, 1, 2, 1, 1, 5, 0]);
+/// # assert_eq!(agent.join().unwrap()[0].version, rust_snmp::types::SnmpVersion::V2c);
+/// ```
+///
+/// ```no_run
+/// use rust_snmp::client::{Credentials, Snmp};
+/// use rust_snmp::snmpv3::Authentication;
+/// let snmp = Snmp::new("demo.snmplabs.com:161".to_owned(), Credentials::V3 {
+///     username: "usr-md5-none".to_owned(),
+///     authentication: Some(Authentication::Md5("authkey1".to_owned())),
+///     privacy: None,
+/// });
+/// for (oid, value) in snmp.walk(vec![1, 3, 6, 1, 2, 1, 1]).unwrap() {
+///     println!("{} = {}", oid, value);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Snmp {
+    /// The address of the agent.
+    pub address: String,
+    /// The port of the agent if the address does not name one.
+    pub port: u16,
+    /// The version to send requests as, and what to authenticate with.
+    pub credentials: Credentials,
+    /// How long to wait for a reply, in milliseconds.
+    pub timeout: u64,
+    /// How many times to resend a request if no reply arrives.
+    /// The timeout is doubled for every retry.
+    pub retries: u32,
+    engine: Mutex<Option<Engine>>,
+}
+
+impl Snmp {
+    /// Creates a client for the agent at the given address.
+    /// Defaults the port to 161, timeout to 1000ms and retries to 0.
+    pub fn new(address: String, credentials: Credentials) -> Snmp {
+        Snmp {
+            address: address,
+            port: snmpv1::DEFAULT_PORT,
+            credentials: credentials,
+            timeout: 1000,
+            retries: 0,
+            engine: Mutex::new(None),
+        }
+    }
+
+    /// Sets the port to send to if the address does not name one.
+    pub fn with_port(mut self, port: u16) -> Snmp {
+        self.port = port;
+        self
+    }
+
+    /// Sets how long to wait for a reply, in milliseconds.
+    pub fn with_timeout(mut self, timeout: u64) -> Snmp {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many times to resend a request if no reply arrives.
+    pub fn with_retries(mut self, retries: u32) -> Snmp {
+        self.retries = retries;
+        self
+    }
+
+    /// Asks the agent for the value of the OID.
+    pub fn get<O: Into<Oid>>(&self, oid: O) -> Result<Message, SnmpError> {
+        let oid = oid.into();
+        match self.credentials {
+            Credentials::V1 { .. } | Credentials::V2c { .. } => self.v1_request(oid).send(),
+            Credentials::V3 { .. } => self.with_v3_request(oid, |request| request.send()),
+        }
+    }
+
+    /// Asks the agent for the OID following the given one, and its value.
+    pub fn get_next<O: Into<Oid>>(&self, oid: O) -> Result<Message, SnmpError> {
+        let oid = oid.into();
+        match self.credentials {
+            Credentials::V1 { .. } | Credentials::V2c { .. } => self.v1_request(oid).send_getnext(),
+            Credentials::V3 { .. } => self.with_v3_request(oid, |request| request.send_getnext()),
+        }
+    }
+
+    /// Asks the agent to assign the value to the OID.
+    pub fn set<O: Into<Oid>>(&self, oid: O, value: SnmpType) -> Result<Message, SnmpError> {
+        let oid = oid.into();
+        match self.credentials {
+            Credentials::V1 { .. } | Credentials::V2c { .. } => self.v1_request(oid).set(value),
+            Credentials::V3 { .. } => self.with_v3_request(oid, |request| request.set(value.clone())),
+        }
+    }
+
+    /// Walks the subtree below the OID, returning every OID and value in it in lexicographic order.
+    pub fn walk<O: Into<Oid>>(&self, oid: O) -> Result<Vec<(Oid, SnmpType)>, SnmpError> {
+        let oid = oid.into();
+        match self.credentials {
+            Credentials::V1 { .. } | Credentials::V2c { .. } => self.v1_request(oid).walk(),
+            Credentials::V3 { .. } => self.with_v3_request(oid, |request| request.walk()),
+        }
+    }
+
+    fn v1_request(&self, oid: Oid) -> snmpv1::Request {
+        let (community, version) = match self.credentials {
+            Credentials::V1 { ref community } => (community.clone(), SnmpVersion::V1),
+            Credentials::V2c { ref community } => (community.clone(), SnmpVersion::V2c),
+            Credentials::V3 { .. } => unreachable!("SNMPv3 credentials make SNMPv3 requests"),
+        };
+        let mut request = snmpv1::Request::new(self.address.clone(), community, oid)
+            .with_version(version)
+            .with_port(self.port)
+            .with_retries(self.retries);
+        request.timeout = self.timeout;
+        request
+    }
+
+    /// Sends a SNMPv3 request with the cached engine, discovering it first if it is unknown,
+    /// and again if the agent reports the request out of its time window.
+    fn with_v3_request<T, F>(&self, oid: Oid, send: F) -> Result<T, SnmpError>
+        where F: Fn(&snmpv3::Request) -> Result<T, SnmpError> {
+        let mut request = match self.credentials {
+            Credentials::V3 { ref username, ref authentication, ref privacy } => {
+                let mut request = snmpv3::Request::new(self.address.clone(), username.clone(), oid)
+                    .with_port(self.port);
+                request.authentication = authentication.clone();
+                request.privacy = privacy.clone();
+                request
+            }
+            _ => unreachable!("SNMPv1 and SNMPv2c credentials make community requests"),
+        };
+        request.timeout = self.timeout;
+        request.retries = self.retries;
+
+        let cached = self.engine.lock().unwrap().clone();
+        let rediscover = cached.is_some();
+        request.engine = match cached {
+            Some(engine) => Some(engine),
+            None => Some(self.discover(&request)?),
+        };
+        match send(&request) {
+            Err(SnmpError::ReportError(SnmpV3Error::NotInTimeWindow)) if rediscover => {
+                request.engine = Some(self.discover(&request)?);
+                send(&request)
+            }
+            result => result,
+        }
+    }
+
+    fn discover(&self, request: &snmpv3::Request) -> Result<Engine, SnmpError> {
+        let engine = request.discover()?;
+        *self.engine.lock().unwrap() = Some(engine.clone());
+        Ok(engine)
+    }
+}
@@ -9,6 +9,37 @@
 //! Contains functions and structs for sending and receiving SNMP messages.
 extern crate byteorder;
 extern crate rand;
+extern crate md5;
+extern crate sha1;
+extern crate sha2;
+extern crate hmac;
+extern crate des;
+extern crate cbc;
+extern crate aes;
+extern crate cfb_mode;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+
+// Without the `log` feature the logging macros do nothing, and their arguments are never evaluated.
+#[cfg(not(feature = "log"))]
+macro_rules! debug {
+    ($($arg:tt)*) => { if false { let _ = format!($($arg)*); } };
+}
+#[cfg(not(feature = "log"))]
+macro_rules! trace {
+    ($($arg:tt)*) => { if false { let _ = format!($($arg)*); } };
+}
+
 pub(crate) mod traits;
+#[cfg(feature = "async")]
+pub(crate) mod asynchronous;
 pub mod types;
 pub mod snmpv1;
+pub mod snmpv3;
+pub mod table;
+pub mod client;
+#[cfg(feature = "mib")]
+pub mod mib;
@@ -0,0 +1,266 @@
+//! Contains a minimal MIB file parser, for resolving names to OIDs. Requires the `mib` feature.
+//!
+//! Only the OID assignments are read, such as `ifEntry OBJECT IDENTIFIER ::= { ifTable 1 }`
+//! and the `::= { parent n }` at the end of OBJECT-TYPE and similar definitions.
+//! Everything else in the file is skipped.
+
+use std::collections::HashMap;
+use std::{fs, mem};
+use std::path::Path;
+use std::sync::RwLock;
+use types::*;
+
+/// The definitions that name the OIDs they are assigned to, besides OBJECT IDENTIFIER.
+const NAMED_MACROS: &[&str] = &["OBJECT-TYPE", "MODULE-IDENTITY", "OBJECT-IDENTITY", "NOTIFICATION-TYPE",
+                                "OBJECT-GROUP", "NOTIFICATION-GROUP", "MODULE-COMPLIANCE", "AGENT-CAPABILITIES"];
+
+/// The roots every MIB builds on, as defined in SNMPv2-SMI.
+const ROOTS: &[(&str, &[u32])] = &[
+    ("ccitt",           &[0]),
+    ("iso",             &[1]),
+    ("joint-iso-ccitt", &[2]),
+    ("org",             &[1, 3]),
+    ("dod",             &[1, 3, 6]),
+    ("internet",        &[1, 3, 6, 1]),
+    ("directory",       &[1, 3, 6, 1, 1]),
+    ("mgmt",            &[1, 3, 6, 1, 2]),
+    ("mib-2",           &[1, 3, 6, 1, 2, 1]),
+    ("transmission",    &[1, 3, 6, 1, 2, 1, 10]),
+    ("experimental",    &[1, 3, 6, 1, 3]),
+    ("private",         &[1, 3, 6, 1, 4]),
+    ("enterprises",     &[1, 3, 6, 1, 4, 1]),
+    ("security",        &[1, 3, 6, 1, 5]),
+    ("snmpV2",          &[1, 3, 6, 1, 6]),
+    ("snmpDomains",     &[1, 3, 6, 1, 6, 1]),
+    ("snmpProxys",      &[1, 3, 6, 1, 6, 2]),
+    ("snmpModules",     &[1, 3, 6, 1, 6, 3]),
+];
+
+static INSTALLED: RwLock<Option<Mib>> = RwLock::new(None);
+
+/// Resolves names to OIDs from the definitions in MIB files.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::mib::Mib;
+/// let mut mib = Mib::new();
+/// mib.load("
+///     EXAMPLE-MIB DEFINITIONS ::= BEGIN
+///     -- Parents may be defined after their children.
+///     exampleTemperature OBJECT-TYPE
+///         SYNTAX      Integer32
+///         MAX-ACCESS  read-only
+///         STATUS      current
+///         DESCRIPTION \"The temperature, as an OBJECT-TYPE would say.\"
+///         ::= { exampleSensors 1 }
+///     exampleSensors OBJECT IDENTIFIER ::= { example 2 }
+///     example OBJECT IDENTIFIER ::= { enterprises 99999 }
+///     END
+/// ");
+/// assert_eq!(mib.resolve("exampleTemperature").unwrap().arcs(), &[1, 3, 6, 1, 4, 1, 99999, 2, 1]);
+/// assert_eq!(mib.parse_oid("exampleTemperature.0").unwrap().arcs(), &[1, 3, 6, 1, 4, 1, 99999, 2, 1, 0]);
+///
+/// // Once installed, the names are understood everywhere OIDs are parsed from strings.
+/// mib.install();
+/// let oid: rust_snmp::types::Oid = "exampleSensors.1.0".parse().unwrap();
+/// assert_eq!(oid.arcs(), &[1, 3, 6, 1, 4, 1, 99999, 2, 1, 0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Mib {
+    oids: HashMap<String, Oid>,
+    // Definitions whose parent has not been seen yet, as the name, parent and arcs below the parent.
+    pending: Vec<(String, String, Vec<u32>)>,
+}
+
+impl Default for Mib {
+    fn default() -> Mib {
+        Mib::new()
+    }
+}
+
+impl Mib {
+    /// Creates a resolver that knows the standard roots, such as `internet`, `mib-2` and `enterprises`.
+    pub fn new() -> Mib {
+        let oids = ROOTS.iter().map(|&(name, arcs)| (name.to_owned(), arcs.into())).collect();
+        Mib { oids: oids, pending: Vec::new() }
+    }
+
+    /// Reads the OID definitions in the text of a MIB file, returning how many were found.
+    /// Definitions below parents that are not known yet are resolved once a later file defines them.
+    /// SNMPv1 TRAP-TYPE definitions assign a trap number rather than an OID, so they are skipped.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::mib::Mib;
+    /// let mut mib = Mib::new();
+    /// let found = mib.load("
+    ///     exampleTrap TRAP-TYPE
+    ///         ENTERPRISE example
+    ///         ::= 1
+    ///     example OBJECT IDENTIFIER ::= { iso(1) org(3) dod(6) internet(1) private(4) enterprises(1) 99999 }
+    /// ");
+    /// assert_eq!(found, 1);
+    /// assert!(mib.resolve("exampleTrap").is_none());
+    /// assert_eq!(mib.resolve("example").unwrap().arcs(), &[1, 3, 6, 1, 4, 1, 99999]);
+    /// ```
+    pub fn load(&mut self, text: &str) -> usize {
+        let tokens = tokenize(text);
+        let mut found = 0;
+        let mut name: Option<&str> = None;
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = tokens[i];
+            if is_name(token) {
+                let next = tokens.get(i + 1).cloned().unwrap_or("");
+                let object_identifier = next == "OBJECT" && tokens.get(i + 2) == Some(&"IDENTIFIER") &&
+                                        tokens.get(i + 3) == Some(&"::=");
+                if object_identifier || NAMED_MACROS.contains(&next) {
+                    name = Some(token);
+                }
+            } else if token == "::=" && tokens.get(i + 1) == Some(&"{") {
+                if let Some(name) = name.take() {
+                    let end = tokens[i..].iter().position(|t| *t == "}").map_or(tokens.len(), |end| i + end);
+                    match parse_assignment(&tokens[i + 2..end]) {
+                        Some((Some(parent), arcs)) => {
+                            self.pending.push((name.to_owned(), parent.to_owned(), arcs));
+                            found += 1;
+                        },
+                        Some((None, arcs)) => {
+                            self.oids.insert(name.to_owned(), arcs.into());
+                            found += 1;
+                        },
+                        None => (),
+                    }
+                    i = end;
+                }
+            } else if token == "::=" {
+                // Anything else assigned, such as a type, is not an OID, so the name does not carry over.
+                name = None;
+            }
+            i += 1;
+        }
+        self.resolve_pending();
+        found
+    }
+
+    /// Reads the OID definitions in a MIB file, returning how many were found.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, SnmpError> {
+        let text = fs::read(path)?;
+        Ok(self.load(&String::from_utf8_lossy(&text)))
+    }
+
+    /// Returns the OID with the name, if it is known.
+    pub fn resolve(&self, name: &str) -> Option<&Oid> {
+        self.oids.get(name)
+    }
+
+    /// Parses an OID in dotted-decimal, or starting with a name this resolver knows, as in "ifDescr.1".
+    pub fn parse_oid(&self, s: &str) -> Result<Oid, SnmpError> {
+        let s = s.strip_prefix('.').unwrap_or(s);
+        let mut parts = s.splitn(2, '.');
+        let first = parts.next().unwrap_or("");
+        match self.resolve(first) {
+            Some(oid) => {
+                let mut arcs = oid.arcs().to_vec();
+                if let Some(rest) = parts.next() {
+                    for arc in rest.split('.') {
+                        arcs.push(arc.parse::<u32>().map_err(|_| SnmpError::InvalidOid)?);
+                    }
+                }
+                Ok(arcs.into())
+            },
+            None => s.parse(),
+        }
+    }
+
+    /// Makes `Oid::from_str` fall back to this resolver for names it does not know,
+    /// replacing any resolver installed before.
+    pub fn install(self) {
+        let mut installed = INSTALLED.write().unwrap_or_else(|e| e.into_inner());
+        *installed = Some(self);
+    }
+
+    /// Resolves definitions whose parents have become known, until no more can be.
+    fn resolve_pending(&mut self) {
+        loop {
+            let before = self.pending.len();
+            let pending = mem::take(&mut self.pending);
+            for (name, parent, arcs) in pending {
+                let resolved = self.oids.get(&parent).map(|oid| {
+                    let mut full = oid.arcs().to_vec();
+                    full.extend(&arcs);
+                    full
+                });
+                match resolved {
+                    Some(full) => { self.oids.insert(name, full.into()); },
+                    None => self.pending.push((name, parent, arcs)),
+                }
+            }
+            if self.pending.len() == before {
+                break;
+            }
+        }
+    }
+}
+
+/// Looks the name up in the installed resolver, if there is one.
+pub(crate) fn resolve_installed(name: &str) -> Option<Oid> {
+    let installed = INSTALLED.read().unwrap_or_else(|e| e.into_inner());
+    installed.as_ref().and_then(|mib| mib.resolve(name).cloned())
+}
+
+/// Splits MIB text into tokens, dropping comments and quoted strings.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if rest.starts_with("--") {
+            // Comments run to the end of the line.
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if rest.starts_with('"') {
+            // Strings, such as descriptions, may run over several lines.
+            rest = rest[1..].find('"').map_or("", |end| &rest[end + 2..]);
+        } else {
+            // Braces, parentheses and separators are tokens by themselves.
+            let end = rest.find(|c: char| c.is_whitespace() || "{}(),;\"".contains(c)).unwrap_or(rest.len());
+            let end = if end == 0 { 1 } else { end };
+            tokens.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+    tokens
+}
+
+/// Returns true if the token can name an OID, which starts with a lowercase letter.
+fn is_name(token: &str) -> bool {
+    token.chars().next().is_some_and(|c| c.is_ascii_lowercase())
+}
+
+/// Parses the inside of `{ parent 1 2 }` or `{ iso org(3) dod(6) }` into the parent and the arcs below it.
+/// An OID starting with a name and its number, as in `{ iso(1) org(3) }`, has no parent, and its arcs
+/// start from the root.
+fn parse_assignment<'a>(tokens: &[&'a str]) -> Option<(Option<&'a str>, Vec<u32>)> {
+    let (first, rest) = tokens.split_first()?;
+    let (parent, mut arcs, mut i) = if rest.first() == Some(&"(") {
+        (None, vec![rest.get(1)?.parse::<u32>().ok()?], 3)
+    } else {
+        (Some(*first), Vec::new(), 0)
+    };
+    while i < rest.len() {
+        match rest[i].parse::<u32>() {
+            Ok(arc) => arcs.push(arc),
+            // A name with its number in parentheses, as in org(3).
+            Err(_) if rest.get(i + 1) == Some(&"(") => {
+                arcs.push(rest.get(i + 2)?.parse::<u32>().ok()?);
+                i += 3;
+            },
+            Err(_) => return None,
+        }
+        i += 1;
+    }
+    Some((parent, arcs))
+}
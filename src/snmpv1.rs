@@ -1,106 +1,342 @@
 //! Contains functions and structs for sending and receiving SNMPv1 messages.
-use std::net::UdpSocket;
-use std::{io, time};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::io::{Read, Write};
+use std::{cmp, io, mem, slice, thread, time};
+use std::borrow::Cow;
+use std::sync::Once;
+use std::sync::atomic::{AtomicU32, Ordering};
 use types::*;
 use traits::*;
 use rand;
+#[cfg(feature = "async")]
+use std::future::{self, Future};
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+#[cfg(feature = "async")]
+use std::sync::Mutex;
+#[cfg(feature = "async")]
+use tokio;
+#[cfg(feature = "async")]
+use asynchronous;
 
-/// Contains a SNMP response and metadata from it.
-#[derive(Debug)]
-pub struct Message {
-    packet: Vec<u8>,
-    community: String,
-    response_id: i64,
-    data: SnmpType
+const SNMP_GET_REQUEST_CODE: u8      = 0xA0;
+const SNMP_GET_NEXT_REQUEST_CODE: u8 = 0xA1;
+const SNMP_SET_REQUEST_CODE: u8      = 0xA3;
+const SNMP_TRAP_CODE: u8             = 0xA4;
+const SNMP_GET_BULK_REQUEST_CODE: u8 = 0xA5;
+const SNMP_INFORM_REQUEST_CODE: u8   = 0xA6;
+
+/// The port agents listen on, which is used for addresses that do not name one.
+pub const DEFAULT_PORT: u16 = 161;
+
+/// The port managers listen for traps on, which is used for addresses that do not name one.
+pub const DEFAULT_TRAP_PORT: u16 = 162;
+
+/// The largest message that fits in a UDP datagram, which requests accept by default.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 65507;
+
+/// The largest datagram UDP can carry, so no reply needs a larger buffer.
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+/// The most successors `bulk_walk` asks for in one GetBulkRequest.
+const SNMP_MAX_BULK_REPETITIONS: u32 = 100;
+
+/// The most variables a walk returns by default, before giving up on the agent.
+pub const DEFAULT_MAX_WALK_VARS: usize = 100_000;
+
+/// A PDU of any type but a SNMPv1 trap, which is laid out the same way for every SNMP version.
+/// SNMPv1 and SNMPv2c send it in a `CommunityMessage`, and SNMPv3 in a `ScopedPdu`.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::snmpv1::Pdu;
+/// use rust_snmp::types::{PduType, SnmpType};
+///
+/// let pdu = Pdu::new(PduType::GetRequest, 7, vec![(vec![1, 3, 6, 1, 2, 1, 1, 5, 0].into(), SnmpType::SnmpNull)]);
+/// let bytes = pdu.encode();
+/// assert_eq!(&bytes[..8], &[0xA0, 0x19, 0x02, 0x01, 0x07, 0x02, 0x01, 0x00]);
+///
+/// // Request IDs take as few bytes as they can, with a leading zero byte when the high bit is set.
+/// for &(request_id, ref encoded) in &[(0, vec![0x02, 0x01, 0x00]),
+///                                    (127, vec![0x02, 0x01, 0x7F]),
+///                                    (128, vec![0x02, 0x02, 0x00, 0x80]),
+///                                    (255, vec![0x02, 0x02, 0x00, 0xFF]),
+///                                    (256, vec![0x02, 0x02, 0x01, 0x00]),
+///                                    (0x80000000, vec![0x02, 0x05, 0x00, 0x80, 0x00, 0x00, 0x00])] {
+///     let bytes = Pdu::new(PduType::GetRequest, request_id, Vec::new()).encode();
+///     assert_eq!(&bytes[2..2 + encoded.len()], &encoded[..]);
+///     assert_eq!(Pdu::decode(&bytes).unwrap().request_id, request_id);
+/// }
+///
+/// let decoded = Pdu::decode(&bytes).unwrap();
+/// assert_eq!(decoded.pdu_type, PduType::GetRequest);
+/// assert_eq!(decoded.request_id, 7);
+/// assert_eq!(decoded.varbinds[0].0.arcs(), &[1, 3, 6, 1, 2, 1, 1, 5, 0]);
+/// assert!(Pdu::decode(&bytes[..bytes.len() - 1]).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pdu {
+    /// The type of the PDU, such as a GetRequest or a response.
+    pub pdu_type: PduType,
+    /// The request ID.
+    pub request_id: u32,
+    /// The error status, or the non-repeaters of a GetBulkRequest.
+    pub error_status: i64,
+    /// The error index, or the max-repetitions of a GetBulkRequest.
+    pub error_index: i64,
+    /// The OIDs and values of the variable bindings.
+    pub varbinds: Vec<(Oid, SnmpType)>,
 }
 
-/// Holds and parses SNMPv1 packets.
-impl Message {
-    fn from_packet(packet: &[u8]) -> Result<Self, SnmpError> {
-        // Check that the packet is as long as it needs to be.
-        if packet.len() < 2 || packet.len() - 2 != packet[1] as usize {
-            return Err(SnmpError::PacketTooShort);
+impl Pdu {
+    /// Creates a PDU with no error status or index.
+    pub fn new(pdu_type: PduType, request_id: u32, varbinds: Vec<(Oid, SnmpType)>) -> Pdu {
+        Pdu {
+            pdu_type: pdu_type,
+            request_id: request_id,
+            error_status: 0,
+            error_index: 0,
+            varbinds: varbinds,
         }
+    }
 
-        // Confirm that the first bit is the SNMP flag.
-        if packet[0] != 0x30 {
-            return Err(SnmpError::ParsingError);
+    /// Encodes the PDU, without anything around it.
+    ///
+    /// The OIDs are not validated. One that fails `Oid::validate`, such as one starting with an arc
+    /// above 2, can not be encoded as it is and comes out as a different OID, so validate OIDs
+    /// that come from elsewhere first. Requests do this before sending.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Pdu;
+    /// use rust_snmp::types::{Oid, PduType, SnmpType};
+    /// let oid: Oid = vec![3, 1].into();
+    /// assert!(oid.validate().is_err());
+    /// let pdu = Pdu::new(PduType::GetRequest, 1, vec![(oid, SnmpType::SnmpNull)]);
+    /// assert_eq!(Pdu::decode(&pdu.encode()).unwrap().varbinds[0].0.arcs(), &[2, 41]);
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        let mut varbinds = Vec::with_capacity(20 * self.varbinds.len());
+        for (oid, value) in &self.varbinds {
+            encode_varbind(oid, value, &mut varbinds);
         }
+        encode_pdu_varbinds(self.pdu_type.into(), self.request_id, (self.error_status, self.error_index), varbinds)
+    }
 
-        let mut iterator = packet[2..].iter();
+    /// Decodes a PDU that is not wrapped in anything.
+    pub fn decode(bytes: &[u8]) -> Result<Pdu, SnmpError> {
+        Pdu::decode_with_limits(bytes, &DecodeLimits::default())
+    }
 
-        // Confirm the protocol is SNMPv1.
-        match extract_value(&mut iterator)? {
-            SnmpType::SnmpInteger(i) => if i != 0 { return Err(SnmpError::ParsingError); },
-            _ => return Err(SnmpError::ParsingError),
-        };
-        
-        // Get the SNMP community.
-        let community = match extract_value(&mut iterator)? {
-            SnmpType::SnmpString(s) => s,
-            _ => return Err(SnmpError::ParsingError),
-        };
+    /// Decodes a PDU like `decode`, within the given limits.
+    pub fn decode_with_limits(bytes: &[u8], limits: &DecodeLimits) -> Result<Pdu, SnmpError> {
+        locate_truncation(bytes, read_pdu(&mut bytes.iter(), limits), limits)
+    }
+}
 
-        // Confirm PDU type GetResponse.
-        if *iterator.next().ok_or(SnmpError::ParsingError)? != 0xA2 {
-            return Err(SnmpError::ParsingError);
-        }
+/// A SNMPv1 or SNMPv2c message, which is a PDU sent with a version and community.
+/// It can be encoded to and decoded from bytes without any sockets, such as to read packets
+/// captured off the network.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::snmpv1::{CommunityMessage, Request};
+/// use rust_snmp::types::{PduType, SnmpError, SnmpVersion};
+///
+/// let mut request = Request::new("127.0.0.1:161".to_owned(), "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0]);
+/// request.request_id = 7;
+/// let mut packet = [0; 64];
+/// let length = request.write_packet(&mut packet).unwrap();
+///
+/// let message = CommunityMessage::decode(&packet[..length]).unwrap();
+/// assert_eq!(message.version, SnmpVersion::V1);
+/// assert_eq!(message.community, b"public");
+/// assert_eq!(message.pdu.pdu_type, PduType::GetRequest);
+/// assert_eq!(message.pdu.request_id, 7);
+/// assert_eq!(message.pdu.varbinds[0].0.arcs(), &[1, 3, 6, 1, 2, 1, 1, 5, 0]);
+/// assert!(message.pdu.varbinds[0].1.is_null());
+/// assert_eq!(message.encode(), &packet[..length]);
+///
+/// // A packet cut short says which value is missing bytes.
+/// match CommunityMessage::decode(&packet[..length - 1]) {
+///     Err(SnmpError::PacketTooShort { offset: 2, expected, actual }) => assert_eq!(actual, expected - 1),
+///     other => panic!("expected PacketTooShort, got {:?}", other),
+/// }
+///
+/// // Here the message is complete, but its PDU claims a byte more than it holds.
+/// let mut inner = packet[..length].to_vec();
+/// let pdu = 2 + 3 + 2 + b"public".len();
+/// inner[pdu + 1] += 1;
+/// match CommunityMessage::decode(&inner) {
+///     Err(SnmpError::PacketTooShort { offset, expected, actual }) => {
+///         assert_eq!((offset, expected - actual), (pdu + 2, 1));
+///     },
+///     other => panic!("expected PacketTooShort, got {:?}", other),
+/// }
+///
+/// // Padding some agents add after the message is skipped.
+/// let mut padded = packet[..length].to_vec();
+/// padded.extend_from_slice(&[0, 0, 0, 0]);
+/// assert_eq!(CommunityMessage::decode(&padded).unwrap().encode(), &packet[..length]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CommunityMessage {
+    /// The SNMP version of the message.
+    pub version: SnmpVersion,
+    /// The community of the message, which may be any bytes.
+    pub community: Vec<u8>,
+    /// The PDU the message carries.
+    pub pdu: Pdu,
+}
 
-        // Get PDU length.
-        iterator.next().ok_or(SnmpError::ParsingError)?;
-        
-        // Get Request ID.
-        let response_id = match extract_value(&mut iterator)? {
-            SnmpType::SnmpInteger(i) => i,
-            _ => return Err(SnmpError::ParsingError),
-        };
-        
-        // Get error type.
-        match extract_value(&mut iterator)? {
-            SnmpType::SnmpInteger(i) => if i != 0 {
-                return Err(SnmpError::ResponseError(i));
-            },
-            _ => return Err(SnmpError::ParsingError),
-        };
+impl CommunityMessage {
+    /// Encodes the message into the bytes sent over the network.
+    /// The OIDs are not validated, as with `Pdu::encode`.
+    pub fn encode(&self) -> Vec<u8> {
+        encode_message(self.version, &self.community, self.pdu.encode())
+    }
+
+    /// Decodes a message from the bytes sent over the network. SNMPv1 traps are laid out
+    /// differently, and are read by `TrapListener` instead.
+    ///
+    /// #Examples
+    /// A message cut short anywhere is rejected with an error instead of being read past its end,
+    /// even when its outer sequence claims to be complete.
+    /// ```
+    /// use rust_snmp::snmpv1::{CommunityMessage, Pdu};
+    /// use rust_snmp::types::{PduType, SnmpType, SnmpVersion};
+    ///
+    /// let pdu = Pdu::new(PduType::Response, 42, vec![(vec![1, 3, 6, 1, 2, 1, 1, 5, 0].into(), SnmpType::SnmpNull)]);
+    /// let packet = CommunityMessage { version: SnmpVersion::V2c, community: b"public".to_vec(), pdu: pdu }.encode();
+    /// assert!(CommunityMessage::decode(&packet).is_ok());
+    /// for keep in 0..packet.len() - 2 {
+    ///     let mut truncated = packet[..2 + keep].to_vec();
+    ///     truncated[1] = keep as u8;
+    ///     assert!(CommunityMessage::decode(&truncated).is_err());
+    /// }
+    /// ```
+    pub fn decode(packet: &[u8]) -> Result<CommunityMessage, SnmpError> {
+        CommunityMessage::decode_with_limits(packet, &DecodeLimits::default())
+    }
+
+    /// Decodes a message like `decode`, within the given limits.
+    pub fn decode_with_limits(packet: &[u8], limits: &DecodeLimits) -> Result<CommunityMessage, SnmpError> {
+        let mut iterator = packet.iter();
+        let (version, community) = locate_truncation(packet, read_message_header(&mut iterator), limits)?;
+        Ok(CommunityMessage {
+            version: version,
+            community: community,
+            pdu: locate_truncation(packet, read_pdu(&mut iterator, limits), limits)?,
+        })
+    }
+}
+
+/// Where request IDs come from.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::snmpv1::{RequestBuilder, RequestIdSource};
+/// let first = RequestBuilder::new()
+///     .address("127.0.0.1:161").community("public").oid(vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+///     .request_id_source(RequestIdSource::Sequential)
+///     .build().unwrap();
+/// let second = RequestIdSource::Sequential.next();
+/// assert_eq!(second, first.request_id.wrapping_add(1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestIdSource {
+    /// A new random number for every request.
+    Random,
+    /// A counter shared by the whole process, starting at a random number and counting up by one
+    /// for every request, so requests are easy to follow in a packet capture and never collide
+    /// until the counter wraps around.
+    Sequential,
+}
 
-        // Get error index.
-        match extract_value(&mut iterator)? {
-            SnmpType::SnmpInteger(i) => if i != 0 {
-                return Err(SnmpError::ResponseError(i));
+/// The next request ID handed out by `RequestIdSource::Sequential`.
+static NEXT_REQUEST_ID: AtomicU32 = AtomicU32::new(0);
+static SEED_REQUEST_ID: Once = Once::new();
+
+impl RequestIdSource {
+    /// Returns a request ID from the source.
+    pub fn next(self) -> u32 {
+        match self {
+            RequestIdSource::Random => rand::random::<u32>(),
+            RequestIdSource::Sequential => {
+                SEED_REQUEST_ID.call_once(|| NEXT_REQUEST_ID.store(rand::random::<u32>(), Ordering::Relaxed));
+                NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
             },
+        }
+    }
+}
+
+/// Contains a SNMP response and metadata from it.
+///
+/// The value can be read in two ways. The `as_` methods return it only if it has exactly
+/// the type asked for, while the `to_` methods and `display` convert it where that makes sense,
+/// such as an integer into a string or a counter into an `i64`.
+///
+/// #Examples
+/// ```no_run
+/// use rust_snmp::snmpv1::Request;
+/// let request = Request::new("demo.snmplabs.com:161", "public", vec![1, 3, 6, 1, 2, 1, 1, 7, 0]);
+/// let message = request.send().unwrap();
+/// // sysServices.0 is an INTEGER, so it is an i64 but not a string, though it displays as one.
+/// assert!(message.as_i64().is_some());
+/// assert!(message.as_str().is_none());
+/// println!("sysServices.0 = {}", message.display());
+/// ```
+#[derive(Debug)]
+pub struct Message {
+    packet: Vec<u8>,
+    community: Vec<u8>,
+    response_id: u32,
+    pdu_type: PduType,
+    varbinds: Vec<(Oid, SnmpType)>,
+}
+
+/// Holds and parses SNMPv1 packets.
+impl Message {
+    fn from_packet(packet: &[u8], request_id: u32, limits: &DecodeLimits) -> Result<Self, SnmpError> {
+        let mut iterator = packet.iter();
+        let (version, community) = locate_truncation(packet, read_message_header(&mut iterator), limits)?;
+        // The community is the credential, so it is kept out of the log.
+        trace!("parsed the header of a {:?} message", version);
+        let pdu = locate_truncation(packet, read_pdu(&mut iterator, limits), limits)?;
+        Message::from_pdu(packet, community, pdu, request_id)
+    }
+
+    /// Checks the PDU of a packet, which is laid out the same way for every SNMP version.
+    pub(crate) fn from_pdu(packet: &[u8], community: Vec<u8>, pdu: Pdu, request_id: u32) -> Result<Self, SnmpError> {
+        // Only responses and reports answer a request.
+        match pdu.pdu_type {
+            PduType::Response | PduType::Report => (),
             _ => return Err(SnmpError::ParsingError),
-        };
+        }
 
-        // Confirm next byte indicates a sequence of OID's and their values.
-        if *iterator.next().ok_or(SnmpError::ParsingError)? != 0x30 {
-            return Err(SnmpError::ParsingError);
+        // Make sure this is the reply to our request and not a stray datagram.
+        trace!("parsing the response with request ID {}", pdu.request_id);
+        if pdu.request_id != request_id {
+            trace!("expected request ID {}", request_id);
+            return Err(SnmpError::RequestIdMismatch);
         }
 
-        // Then a length. Not in use as we don't support batch requests.
-        iterator.next().ok_or(SnmpError::ParsingError)?;
+        if pdu.error_status != 0 {
+            trace!("the response has error status {} at index {}", pdu.error_status, pdu.error_index);
+            return Err(SnmpError::ResponseError(pdu.error_status.into(), pdu.error_index));
+        }
 
-        // Then there is the sequence for the first (and only) OID.
-        if *iterator.next().ok_or(SnmpError::ParsingError)? != 0x30 {
+        trace!("parsed {} variable bindings", pdu.varbinds.len());
+        if pdu.varbinds.is_empty() {
             return Err(SnmpError::ParsingError);
         }
 
-        // With an associated length...
-        iterator.next().ok_or(SnmpError::ParsingError)?;
-        
-        // Get the OID...
-        match extract_value(&mut iterator)? {
-            SnmpType::SnmpObjectID(o) => o,
-            _ => return Err(SnmpError::ParsingError),
-        };
-
-        // And finally, get the actual data.
-        let datatype = extract_value(&mut iterator)?;
-        
         Ok(Message {
             packet: packet.to_vec(),
             community: community,
-            response_id: response_id,
-            data: datatype,
+            response_id: pdu.request_id,
+            pdu_type: pdu.pdu_type,
+            varbinds: pdu.varbinds,
         })
     }
 
@@ -109,137 +345,2895 @@ impl Message {
         &self.packet
     }
 
+    /// Returns the type of the PDU the agent answered with, which is a response
+    /// unless the agent sent a report to say why it could not answer.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::PduType;
+    ///
+    /// // The agent answers with a Report.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = PduType::Report;
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..1 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 6, 3, 15, 1, 1, 4, 0]);
+    /// assert_eq!(request.send().unwrap().pdu_type(), PduType::Report);
+    /// ```
+    pub fn pdu_type(&self) -> PduType {
+        self.pdu_type
+    }
+
+    /// Returns the community the response was sent with. This is empty for SNMPv3 responses.
+    pub fn community(&self) -> &[u8] {
+        &self.community
+    }
+
+    /// Returns the community the response was sent with as a string,
+    /// replacing any invalid UTF-8 with U+FFFD.
+    pub fn community_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.community)
+    }
+
+    /// Returns every OID and value in the response, in the order the agent sent them.
+    pub fn varbinds(&self) -> &[(Oid, SnmpType)] {
+        &self.varbinds
+    }
+
+    /// Returns the OIDs and values the agent had, leaving out the exceptions a SNMPv2c agent
+    /// answers with for OIDs it does not have, such as NoSuchObject.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::{SnmpType, SnmpVersion};
+    ///
+    /// // The agent echoes the request back, with NoSuchObject for the last variable binding.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     message.pdu.varbinds[1].1 = SnmpType::NoSuchObject;
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..1 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let sys_descr = vec![1, 3, 6, 1, 2, 1, 1, 1, 0];
+    /// let missing = vec![1, 3, 6, 1, 2, 1, 1, 99, 0];
+    /// let request = Request::from_oids(address, "public", vec![sys_descr.clone().into(), missing.clone().into()])
+    ///     .with_version(SnmpVersion::V2c);
+    /// let message = request.send().unwrap();
+    /// let found: Vec<_> = message.successes().map(|&(ref oid, _)| oid.clone()).collect();
+    /// assert_eq!(found, vec![sys_descr.into()]);
+    /// let exceptions: Vec<_> = message.exceptions().collect();
+    /// assert_eq!(exceptions.len(), 1);
+    /// assert_eq!(exceptions[0].0, missing.into());
+    /// match exceptions[0].1 {
+    ///     SnmpType::NoSuchObject => (),
+    ///     ref other => panic!("expected NoSuchObject, got {:?}", other),
+    /// }
+    /// ```
+    pub fn successes(&self) -> impl Iterator<Item = &(Oid, SnmpType)> {
+        self.varbinds.iter().filter(|(_, value)| !value.is_exception())
+    }
+
+    /// Returns the OIDs the agent answered with an exception instead of a value,
+    /// together with the exception.
+    pub fn exceptions(&self) -> impl Iterator<Item = &(Oid, SnmpType)> {
+        self.varbinds.iter().filter(|(_, value)| value.is_exception())
+    }
+
+    /// Returns the value for the OID, if the response holds it.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// let sys_descr = vec![1, 3, 6, 1, 2, 1, 1, 1, 0];
+    /// let sys_name = vec![1, 3, 6, 1, 2, 1, 1, 5, 0];
+    /// let request = Request::from_oids("demo.snmplabs.com:161".to_owned(), "public",
+    ///                                  vec![sys_descr.into(), sys_name.clone().into()]);
+    /// let message = request.send().unwrap();
+    /// println!("sysName = {:?}", message.get(&sys_name.into()));
+    /// ```
+    pub fn get(&self, oid: &Oid) -> Option<&SnmpType> {
+        self.varbinds.iter().find(|(o, _)| o == oid).map(|(_, value)| value)
+    }
+
+    /// Returns the OID the agent answered for, which may differ from the one asked for,
+    /// such as the successor returned for a GetNextRequest.
+    /// If the response holds several variable bindings, this is the first one.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1]);
+    /// let message = request.send_getnext().unwrap();
+    /// println!("{:?} = {:?}", message.oid(), message.value());
+    /// ```
+    pub fn oid(&self) -> &Oid {
+        &self.varbinds[0].0
+    }
+
     /// Returns the data in whatever type it is.
+    /// If the response holds several variable bindings, this is the first one.
     pub fn data(&self) -> &SnmpType {
-        &self.data
+        &self.varbinds[0].1
+    }
+
+    /// Returns the value the agent answered with, to match on its type.
+    /// If the response holds several variable bindings, this is the first one.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::SnmpType;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 10, 1]);
+    /// let message = request.send().unwrap();
+    /// match *message.value() {
+    ///     SnmpType::SnmpCounter32(octets) => println!("{} octets in", octets),
+    ///     ref other => println!("unexpected value {:?}", other),
+    /// }
+    /// if let Some(octets) = message.value().as_counter() {
+    ///     println!("{} octets in", octets);
+    /// }
+    /// ```
+    pub fn value(&self) -> &SnmpType {
+        self.data()
+    }
+
+    /// Returns the number if the value is a SnmpInteger, without converting any other type.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.data().as_integer()
+    }
+
+    /// Returns the string if the value is a SnmpString, without converting any other type.
+    pub fn as_str(&self) -> Option<&str> {
+        self.data().as_str()
+    }
+
+    /// Returns the raw octets if the value is a SnmpString or SnmpOctetString,
+    /// without converting any other type.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        self.data().as_bytes()
+    }
+
+    /// Formats the value as text, whatever its type. Numbers, addresses and OIDs are written
+    /// the usual way, octets that are not text as space-separated hex, and exceptions as
+    /// what they mean. Use `value` and its `Display` to also see the type.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::SnmpType;
+    ///
+    /// // The agent echoes each SetRequest back as the response.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..3 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1, 7, 0]);
+    /// let message = request.set(SnmpType::SnmpInteger(72)).unwrap();
+    /// assert_eq!(message.as_i64(), Some(72));
+    /// assert_eq!(message.as_str(), None);
+    /// assert_eq!(message.display(), "72");
+    ///
+    /// let message = request.set(SnmpType::SnmpString("router".to_owned())).unwrap();
+    /// assert_eq!(message.as_str(), Some("router"));
+    /// assert_eq!(message.as_i64(), None);
+    /// assert_eq!(message.display(), "router");
+    ///
+    /// let message = request.set(SnmpType::SnmpObjectID(vec![1, 3, 6, 1, 4, 1, 8072].into())).unwrap();
+    /// assert_eq!(message.display(), "1.3.6.1.4.1.8072");
+    /// ```
+    pub fn display(&self) -> String {
+        match *self.data() {
+            SnmpType::SnmpInteger(i) => i.to_string(),
+            SnmpType::SnmpString(ref s) => s.clone(),
+            SnmpType::SnmpOctetString(ref bytes) => Hex(bytes).to_string(),
+            SnmpType::SnmpNull => String::new(),
+            SnmpType::SnmpObjectID(ref oid) => oid.to_string(),
+            SnmpType::SnmpIpAddress(ip) => ip.to_string(),
+            SnmpType::SnmpCounter32(c) | SnmpType::SnmpGauge32(c) | SnmpType::SnmpTimeTicks(c) => c.to_string(),
+            SnmpType::SnmpOpaque(ref bytes) => match self.data().as_f64() {
+                Some(number) => number.to_string(),
+                None => Hex(bytes).to_string(),
+            },
+            SnmpType::SnmpCounter64(c) => c.to_string(),
+            ref exception => exception.to_string(),
+        }
     }
 
-    /// Parses the data of the packet as a utf8 string.
+    /// Parses the data of the packet as a utf8 string. This converts a SnmpInteger
+    /// into its decimal digits; use `as_str` to only accept strings. Octet strings that are not
+    /// valid UTF-8 fail with `SnmpError::Utf8`; use `to_string_lossy` to read them anyway.
     pub fn to_string(&self) -> Result<String, SnmpError> {
-        match self.data {
+        match *self.data() {
             SnmpType::SnmpInteger(ref i) => Ok((*i).to_string()),
             SnmpType::SnmpString(ref s) => Ok(s.clone()),
+            SnmpType::SnmpOctetString(ref bytes) => Ok(String::from_utf8(bytes.clone())?),
             _ => Err(SnmpError::InvalidType),
         }
     }
 
-    /// If the message is a SnmpInteger, parses it and returns the number.
+    /// Like `to_string`, but replaces any invalid UTF-8 in an octet string with U+FFFD
+    /// instead of failing, for agents that send Latin-1 or cut a character in half.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::SnmpType;
+    ///
+    /// // The agent echoes the SetRequest back as the response.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..1 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1, 1, 0]);
+    /// let message = request.set(SnmpType::SnmpOctetString(b"caf\xE9 router".to_vec())).unwrap();
+    /// assert!(message.to_string().is_err());
+    /// assert_eq!(message.to_string_lossy().unwrap(), "caf\u{FFFD} router");
+    /// ```
+    pub fn to_string_lossy(&self) -> Result<String, SnmpError> {
+        match *self.data() {
+            SnmpType::SnmpOctetString(ref bytes) => Ok(String::from_utf8_lossy(bytes).into_owned()),
+            _ => self.to_string(),
+        }
+    }
+
+    /// If the message is an octet string, returns its raw bytes without decoding them as text.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SnmpError> {
+        self.data().as_bytes().map(|bytes| bytes.to_vec()).ok_or(SnmpError::InvalidType)
+    }
+
+    /// If the message is an octet string, formats its bytes as lowercase, space-separated hex,
+    /// the same way the value is displayed.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 6, 2]);
+    /// let message = request.send().unwrap();
+    /// println!("{}", message.to_hex_string().unwrap()); // 00 1b 21 3c 9e 6a
+    /// println!("{}", message.to_mac_address().unwrap());
+    /// ```
+    pub fn to_hex_string(&self) -> Result<String, SnmpError> {
+        let bytes = self.data().as_bytes().ok_or(SnmpError::InvalidType)?;
+        Ok(Hex(bytes).to_string())
+    }
+
+    /// If the message is a SnmpInteger, SnmpCounter32, SnmpGauge32 or SnmpTimeTicks,
+    /// parses it and returns the number.
     pub fn to_int(&self) -> Result<i64, SnmpError> {
-        match self.data {
+        match *self.data() {
             SnmpType::SnmpInteger(ref i) => Ok(*i),
+            SnmpType::SnmpCounter32(ref c) => Ok(*c as i64),
+            SnmpType::SnmpGauge32(ref g) => Ok(*g as i64),
+            SnmpType::SnmpTimeTicks(ref t) => Ok(*t as i64),
+            _ => Err(SnmpError::InvalidType),
+        }
+    }
+
+    /// If the message is a SnmpCounter32, SnmpGauge32 or SnmpTimeTicks, returns the unsigned number.
+    pub fn to_u32(&self) -> Result<u32, SnmpError> {
+        match *self.data() {
+            SnmpType::SnmpCounter32(ref c) => Ok(*c),
+            SnmpType::SnmpGauge32(ref g) => Ok(*g),
+            SnmpType::SnmpTimeTicks(ref t) => Ok(*t),
+            _ => Err(SnmpError::InvalidType),
+        }
+    }
+
+    /// If the message is any of the unsigned types, including SnmpCounter64, returns the number.
+    pub fn to_u64(&self) -> Result<u64, SnmpError> {
+        match *self.data() {
+            SnmpType::SnmpCounter64(ref c) => Ok(*c),
+            _ => self.to_u32().map(|n| n as u64),
+        }
+    }
+
+    /// If the message is a SnmpOpaque wrapping a float or double, returns the number.
+    pub fn to_f64(&self) -> Result<f64, SnmpError> {
+        self.data().as_f64().ok_or(SnmpError::InvalidType)
+    }
+
+    /// If the message is a SnmpObjectID, returns the OID.
+    pub fn to_oid(&self) -> Result<Oid, SnmpError> {
+        match *self.data() {
+            SnmpType::SnmpObjectID(ref o) => Ok(o.clone()),
+            _ => Err(SnmpError::InvalidType),
+        }
+    }
+
+    /// If the message is a 6-byte octet string, such as an ifPhysAddress, formats it as
+    /// a MAC address like `00:1b:21:3c:9e:6a`. Any other length is an `InvalidType` error.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::SnmpType;
+    ///
+    /// // The agent echoes each SetRequest back as the response.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..2 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 6, 2]);
+    /// let mac = vec![0x00, 0x1b, 0x21, 0x3c, 0x9e, 0x6a];
+    /// let message = request.set(SnmpType::SnmpOctetString(mac)).unwrap();
+    /// assert_eq!(message.to_mac_address().unwrap(), "00:1b:21:3c:9e:6a");
+    ///
+    /// let message = request.set(SnmpType::SnmpOctetString(vec![0x00, 0x1b, 0x21])).unwrap();
+    /// assert!(message.to_mac_address().is_err());
+    /// ```
+    pub fn to_mac_address(&self) -> Result<String, SnmpError> {
+        match self.data().as_bytes() {
+            Some(bytes) if bytes.len() == 6 => Ok(Hex(bytes).to_string().replace(' ', ":")),
+            _ => Err(SnmpError::InvalidType),
+        }
+    }
+
+    /// If the message is an octet string holding BITS, such as ifStackStatus flags, returns the numbers
+    /// of the set bits, where bit 0 is the most significant bit of the first byte.
+    pub fn to_bits(&self) -> Result<Vec<u32>, SnmpError> {
+        self.data().as_bits().ok_or(SnmpError::InvalidType)
+    }
+
+    /// If the message is a SnmpIpAddress, returns the address.
+    pub fn to_ip(&self) -> Result<Ipv4Addr, SnmpError> {
+        match *self.data() {
+            SnmpType::SnmpIpAddress(ref ip) => Ok(*ip),
+            _ => Err(SnmpError::InvalidType),
+        }
+    }
+
+    /// If the message is a SnmpTimeTicks, converts the hundredths of a second into a Duration.
+    pub fn to_duration(&self) -> Result<time::Duration, SnmpError> {
+        match *self.data() {
+            SnmpType::SnmpTimeTicks(ref t) => Ok(time::Duration::from_millis(*t as u64 * 10)),
             _ => Err(SnmpError::InvalidType),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 /// Contains fields describing a SNMPv1 request as well as functions to send it.
+///
+/// The address can be anything that resolves to a socket address, including IPv6 addresses.
+/// Addresses without a port, such as a bare hostname, are sent to `port`.
+///
+/// #Examples
+/// ```
+/// use std::net::UdpSocket;
+/// use std::thread;
+/// use rust_snmp::snmpv1::Request;
+///
+/// // Answer with sysUpTime.0 = 42 from an agent on the IPv6 loopback, if the host has one.
+/// if let Ok(agent) = UdpSocket::bind("[::1]:0") {
+///     let address = agent.local_addr().unwrap().to_string();
+///     thread::spawn(move || {
+///         let mut buf = [0; 1024];
+///         let (_, from) = agent.recv_from(&mut buf).unwrap();
+///         let mut reply = vec![0x30, 0x2A, 0x02, 0x01, 0x00, 0x04, 0x06];
+///         reply.extend_from_slice(b"public");
+///         reply.extend_from_slice(&[0xA2, 0x1D, 0x02, 0x04, 0x01, 0x02, 0x03, 0x04,
+///                                   0x02, 0x01, 0x00, 0x02, 0x01, 0x00, 0x30, 0x0F, 0x30, 0x0D,
+///                                   0x06, 0x08, 0x2B, 0x06, 0x01, 0x02, 0x01, 0x01, 0x03, 0x00,
+///                                   0x43, 0x01, 0x2A]);
+///         agent.send_to(&reply, from).unwrap();
+///     });
+///
+///     let mut request = Request::new(address, "public".to_owned(), vec![1, 3, 6, 1, 2, 1, 1, 3, 0]);
+///     request.request_id = 0x01020304;
+///     assert_eq!(request.send().unwrap().to_int().unwrap(), 42);
+/// }
+/// ```
+///
+/// A request can serve as a template, copied and adjusted for every device.
+/// ```
+/// use rust_snmp::snmpv1::Request;
+/// let template = Request::new("0.0.0.0:161".to_owned(), "public", vec![1, 3, 6, 1, 2, 1, 1, 3, 0]);
+/// let requests: Vec<Request> = ["192.0.2.1:161", "192.0.2.2:161"].iter().map(|address| {
+///     let mut request = template.clone();
+///     request.address = address.to_string();
+///     request
+/// }).collect();
+/// assert_ne!(requests[0], requests[1]);
+/// assert_eq!(requests[0], Request { address: "192.0.2.1:161".to_owned(), ..template.clone() });
+/// ```
 pub struct Request {
     /// The address to send the request to.
     pub address: String,
-    /// The MIB to ask for.
-    pub mibvals: Vec<u16>,
-    /// The community used to authenticate.
-    pub community: String,
+    /// The port to send the request to if the address does not name one.
+    pub port: u16,
+    /// The OIDs to ask for.
+    pub oids: Vec<Oid>,
+    /// The community used to authenticate, which may be any bytes.
+    pub community: Vec<u8>,
+    /// The SNMP version to send the request as.
+    pub version: SnmpVersion,
     /// The request ID to provide.
     pub request_id: u32,
-    /// How long to wait for a reply.
+    /// Where the request IDs of requests sent through `get` come from.
+    pub request_id_source: RequestIdSource,
+    /// How long to wait for a reply, in milliseconds.
     pub timeout: u64,
+    /// How many times to resend the request if no reply arrives.
+    /// The timeout is doubled for every retry.
+    pub retries: u32,
+    /// Whether to reject responses with a different community than the request.
+    pub check_community: bool,
+    /// The local address to send the request from. Any address is used if `None`.
+    pub bind_addr: Option<SocketAddr>,
+    /// The longest random delay to wait before sending the request and before every retry,
+    /// so that many requests sent at once are spread out. No delay is added if `None`.
+    pub jitter: Option<time::Duration>,
+    /// The transport to send the request over. Only UDP can be sent from a chosen local address,
+    /// or without blocking.
+    pub transport: SnmpTransport,
+    /// The largest response to accept, in bytes. Larger responses fail with `SnmpError::ResponseTooLarge`.
+    pub max_message_size: usize,
+    /// The most variables `walk` and `bulk_walk` return before failing with
+    /// `SnmpError::WalkLimitExceeded`, so an agent that never leaves the subtree can not walk forever.
+    pub max_walk_vars: usize,
+    /// Limits on what the parser accepts in a response, so a hostile agent can not make it do
+    /// unbounded work. Responses past them fail with `SnmpError::LimitExceeded`.
+    pub decode_limits: DecodeLimits,
+    /// The address the request was resolved to by `try_new` or `resolve`, which is sent to instead of
+    /// looking up `address` again. Set this back to `None` after changing `address`.
+    pub target: Option<SocketAddr>,
 }
 
 impl Request {
-    /// Creates a request with only the essential arguments.
-    /// Defaults requestID to a random number, and timeout to 1000ms.
-    pub fn new(address: String, community: String, mibvals: Vec<u16>) -> Request {        
-        Request {
-            address: address,
-            mibvals: mibvals,
-            community: community,
-            request_id: rand::random::<u32>(),
-            timeout: 1000
-        }
+    /// Creates a request for a single OID with only the essential arguments.
+    /// The address can be given as a `&str` or a `String`, the community as a string or as raw bytes,
+    /// and the OID as a list of arcs or as an `Oid` parsed from a string.
+    /// Defaults version to SNMPv1, requestID to a random number, timeout to 1000ms and retries to 0,
+    /// accepts responses up to `DEFAULT_MAX_MESSAGE_SIZE` bytes and does not check the community of the response.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::{read_tlv, Oid, SnmpType};
+    /// let oid: Oid = "1.3.6.1.2.1.1.5.0".parse().unwrap();
+    /// let request = Request::new("demo.snmplabs.com:161", "public", oid);
+    /// assert_eq!(request.address, "demo.snmplabs.com:161");
+    /// assert_eq!(request.oids[0].arcs(), &[1, 3, 6, 1, 2, 1, 1, 5, 0]);
+    ///
+    /// // Arcs are 32 bits wide, so enterprise numbers and large indices fit.
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 4, 1, 2021, 100000]);
+    /// assert_eq!(request.oids[0].arcs()[7], 100000);
+    ///
+    /// // They take up to five bytes when sent, up to the largest arc there is.
+    /// let mut oid = request.oids[0].arcs().to_vec();
+    /// oid.push(4294967295);
+    /// let encoded = SnmpType::SnmpObjectID(oid.clone().into()).encode();
+    /// assert_eq!(encoded, [0x06, 0x0F, 0x2B, 0x06, 0x01, 0x04, 0x01, 0x8F, 0x65, 0x86, 0x8D, 0x20,
+    ///                      0x8F, 0xFF, 0xFF, 0xFF, 0x7F]);
+    /// match read_tlv(&encoded).unwrap() {
+    ///     (SnmpType::SnmpObjectID(decoded), 17) => assert_eq!(decoded.arcs(), &oid[..]),
+    ///     other => panic!("expected the OID back, got {:?}", other),
+    /// }
+    ///
+    /// // Communities are octet strings, so they need not be valid UTF-8.
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(), vec![0x70, 0xFF, 0x00], vec![1, 3, 6]);
+    /// assert_eq!(request.community, [0x70, 0xFF, 0x00]);
+    /// ```
+    pub fn new<A, C, O>(address: A, community: C, oid: O) -> Request
+        where A: Into<String>, C: Into<Vec<u8>>, O: Into<Oid> {
+        Request::from_oids(address, community, vec![oid.into()])
     }
 
-    /// Sends a SMTPv1 message and returns the reply or an error specifiying what went wrong.
+    /// Creates a request like `new`, but resolves the address straight away, so a mistyped
+    /// address fails here rather than when sending. The resolved address is kept in `target`,
+    /// so it is not looked up again for every request sent.
     ///
     /// #Examples
     /// ```
     /// use rust_snmp::snmpv1::Request;
-    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
-    ///                                  "public".to_owned(),
-    ///                                  vec![1, 3, 6, 1, 2, 1, 1, 1, 0]);
-    /// let message = request.send().unwrap();
-    /// let host = message.to_string().unwrap();
-    /// assert_eq!("Linux", &host[..5]);
+    /// let request = Request::try_new("127.0.0.1:161", "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0]).unwrap();
+    /// assert_eq!(request.target, Some("127.0.0.1:161".parse().unwrap()));
+    ///
+    /// // Addresses without a port are sent to port 161.
+    /// let request = Request::try_new("127.0.0.1", "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0]).unwrap();
+    /// assert_eq!(request.target, Some("127.0.0.1:161".parse().unwrap()));
+    /// assert!(Request::try_new("not an address", "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0]).is_err());
     /// ```
-    pub fn send(&self) -> Result<Message, SnmpError> {
-        // Bind to any UDP socket, set timeout to avoid hanging.
-        let socket = UdpSocket::bind("0.0.0.0:0")?;
-        socket.set_read_timeout(Some(time::Duration::from_millis(1000)))?;
-        
-        // Create and send packet
-        let sendpacket = self.createpacket()?;
-        socket.send_to(&sendpacket, &self.address)?;
-
-        // Receive and parse packet
-        let mut receivepacket: [u8; 1024] = [0; 1024];
-        let (length, _) = socket.recv_from(&mut receivepacket)?;
-        // DEBUG TODO REMOVE
-        for i in &receivepacket[0..length] {print!("{:02X} ", i);}
-        Ok(Message::from_packet(&receivepacket[0..length])?)
-    }
-
-    fn createpacket(&self) -> Result<Vec<u8>, io::Error> {
-        let mut buf = Vec::with_capacity(250);
-        let mut mib = Vec::with_capacity(20);
-
-        // Convert MIBs to bytes since each number can be more than one byte big.
-        for mibval in self.mibvals.iter().skip(2) {
-            if mibval > &127u16 {
-                mib.push((128 + (*mibval / 128)) as u8);
-                mib.push((*mibval - ((*mibval / 128) * 128)) as u8);
-            } else {
-                mib.push(*mibval as u8);
-            }
-        }
-        let snmplen = 29 + self.community.len() + mib.len() + 2 - 1;
+    pub fn try_new<A, C, O>(address: A, community: C, oid: O) -> Result<Request, SnmpError>
+        where A: Into<String>, C: Into<Vec<u8>>, O: Into<Oid> {
+        let mut request = Request::new(address, community, oid);
+        request.resolve()?;
+        Ok(request)
+    }
 
-        // SNMP sequence start
-        buf.push(0x30);
-        buf.push((snmplen - 2) as u8);
+    /// Creates a request like `new` for an address that is already known, so it is never resolved.
+    /// Pollers sending to the same hosts over and over can keep their addresses this way.
+    ///
+    /// #Examples
+    /// ```
+    /// use std::net::{Ipv4Addr, SocketAddr};
+    /// use rust_snmp::snmpv1::Request;
+    /// let target = SocketAddr::from((Ipv4Addr::new(192, 0, 2, 1), 161));
+    /// let request = Request::from_addr(target, "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0]);
+    /// assert_eq!(request.address, "192.0.2.1:161");
+    /// assert_eq!(request.target, Some(target));
+    /// ```
+    pub fn from_addr<C: Into<Vec<u8>>, O: Into<Oid>>(target: SocketAddr, community: C, oid: O) -> Request {
+        let mut request = Request::new(target.to_string(), community, oid);
+        request.target = Some(target);
+        request
+    }
 
-        // SNMP version
-        buf.append(&mut 0x00u8.encode_snmp());
+    /// Resolves the address and keeps it in `target`, returning it.
+    /// Call this again to pick up changes to the address, such as a new DNS record.
+    pub fn resolve(&mut self) -> Result<SocketAddr, SnmpError> {
+        let target = resolve(&with_port(&self.address, self.port))?;
+        self.target = Some(target);
+        Ok(target)
+    }
 
-        // Community
-        buf.append(&mut self.community.as_bytes().encode_snmp());
-        
-        // MIB size sequence
-        buf.push(0xA0); // GET request
-        buf.push((19 + mib.len() + 2) as u8); // MIB size
+    /// Sends the request to the port if the address does not name one, as in `"192.0.2.1"`
+    /// or a bare hostname. Addresses such as `"192.0.2.1:1161"` keep their own port.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// for host in &["127.0.0.1", "::1", "[::1]"] {
+    ///     let mut request = Request::new(host.to_string(), "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+    ///         .with_port(1161);
+    ///     assert_eq!(request.resolve().unwrap().port(), 1161);
+    /// }
+    ///
+    /// let mut request = Request::new("127.0.0.1:2161".to_owned(), "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+    ///     .with_port(1161);
+    /// assert_eq!(request.resolve().unwrap().port(), 2161);
+    /// ```
+    pub fn with_port(mut self, port: u16) -> Request {
+        self.port = port;
+        self
+    }
 
-        // Request ID
-        buf.append(&mut self.request_id.encode_snmp());
-        
-        // Error status and index
-        buf.append(&mut 0x00u8.encode_snmp());
-        buf.append(&mut 0x00u8.encode_snmp());
-
-        // Variable binding
-        buf.push(0x30);                      // Start of sequence
-        buf.push((5 + mib.len() + 2) as u8); // Size
-        buf.push(0x30);                      // Start of sequence
-        buf.push((3 + mib.len() + 2) as u8); // Size
-        buf.push(0x06);                      // Object type
-        buf.push((mib.len() - 1 + 2) as u8); // Size
-
-        // MIB
-        buf.push(0x2B);
-        buf.append(&mut mib);
-
-        // Terminate with null
-        buf.push(0x05);
-        buf.push(0x00);
-        Ok(buf)
+    /// Returns the address to send to, resolving it unless it is already known.
+    fn target(&self) -> io::Result<SocketAddr> {
+        match self.target {
+            Some(target) => Ok(target),
+            None => resolve(&with_port(&self.address, self.port)),
+        }
     }
+
+    /// Creates a request for several OIDs, which are all fetched in one round-trip.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::from_oids("demo.snmplabs.com:161".to_owned(),
+    ///                                  "public".to_owned(),
+    ///                                  vec![vec![1, 3, 6, 1, 2, 1, 1, 1, 0].into(),
+    ///                                       vec![1, 3, 6, 1, 2, 1, 1, 5, 0].into()]);
+    /// let message = request.send().unwrap();
+    /// for &(ref oid, ref value) in message.varbinds() {
+    ///     println!("{:?} = {:?}", oid, value);
+    /// }
+    /// ```
+    pub fn from_oids<A: Into<String>, C: Into<Vec<u8>>>(address: A, community: C, oids: Vec<Oid>) -> Request {
+        Request {
+            address: address.into(),
+            port: DEFAULT_PORT,
+            oids: oids,
+            community: community.into(),
+            version: SnmpVersion::V1,
+            request_id: rand::random::<u32>(),
+            request_id_source: RequestIdSource::Random,
+            timeout: 1000,
+            retries: 0,
+            check_community: false,
+            bind_addr: None,
+            jitter: None,
+            transport: SnmpTransport::Udp,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            max_walk_vars: DEFAULT_MAX_WALK_VARS,
+            decode_limits: DecodeLimits::default(),
+            target: None,
+        }
+    }
+
+    /// Sets how many times to resend the request if no reply arrives.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1, 1, 0])
+    ///     .with_retries(3);
+    /// assert_eq!(request.retries, 3);
+    /// ```
+    pub fn with_retries(mut self, retries: u32) -> Request {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets whether to reject responses with a different community than the request,
+    /// returning `SnmpError::CommunityMismatch` instead.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1, 1, 0])
+    ///     .with_community_check(true);
+    /// assert!(request.check_community);
+    /// ```
+    pub fn with_community_check(mut self, check: bool) -> Request {
+        self.check_community = check;
+        self
+    }
+
+    /// Sets the SNMP version to send the request as.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::SnmpVersion;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1, 1, 0])
+    ///     .with_version(SnmpVersion::V2c);
+    /// assert_eq!(request.version, SnmpVersion::V2c);
+    /// ```
+    pub fn with_version(mut self, version: SnmpVersion) -> Request {
+        self.version = version;
+        self
+    }
+
+    /// Sends the request from the given local address, picking the interface and source port.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1, 1, 0])
+    ///     .with_bind_addr("10.0.0.2:16100".parse().unwrap());
+    /// assert_eq!(request.bind_addr.unwrap().port(), 16100);
+    /// ```
+    pub fn with_bind_addr(mut self, bind_addr: SocketAddr) -> Request {
+        self.bind_addr = Some(bind_addr);
+        self
+    }
+
+    /// Sets the transport to send the request over.
+    ///
+    /// #Examples
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use std::net::TcpListener;
+    /// use std::thread;
+    /// use rust_snmp::snmpv1::{CommunityMessage, Request};
+    /// use rust_snmp::types::{PduType, SnmpTransport};
+    ///
+    /// let agent = TcpListener::bind("127.0.0.1:0").unwrap();
+    /// let address = agent.local_addr().unwrap().to_string();
+    /// thread::spawn(move || {
+    ///     let (mut stream, _) = agent.accept().unwrap();
+    ///     let mut request = vec![0; 2];
+    ///     stream.read_exact(&mut request).unwrap();
+    ///     request.resize(2 + request[1] as usize, 0);
+    ///     stream.read_exact(&mut request[2..]).unwrap();
+    ///     // Answer by echoing the request back as a response, split over two writes.
+    ///     let mut message = CommunityMessage::decode(&request).unwrap();
+    ///     message.pdu.pdu_type = PduType::Response;
+    ///     let reply = message.encode();
+    ///     stream.write_all(&reply[..2]).unwrap();
+    ///     stream.write_all(&reply[2..]).unwrap();
+    /// });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+    ///     .with_transport(SnmpTransport::Tcp);
+    /// assert!(request.send().unwrap().value().is_null());
+    /// ```
+    pub fn with_transport(mut self, transport: SnmpTransport) -> Request {
+        self.transport = transport;
+        self
+    }
+
+    /// Sets the largest response to accept, in bytes. Larger responses fail with
+    /// `SnmpError::ResponseTooLarge` rather than being cut off.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::SnmpError;
+    ///
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..1 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+    ///     .with_max_message_size(20);
+    /// match request.send() {
+    ///     Err(SnmpError::ResponseTooLarge) => (),
+    ///     other => panic!("expected the response to be too large, got {:?}", other),
+    /// }
+    /// ```
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Request {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Sets the most variables `walk` and `bulk_walk` return. Defaults to `DEFAULT_MAX_WALK_VARS`.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::{PduType, SnmpError, SnmpType};
+    ///
+    /// // An agent that always answers with an OID one level further down, so a walk never ends.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     let mut arcs = message.pdu.varbinds[0].0.arcs().to_vec();
+    /// #     arcs.push(1);
+    /// #     message.pdu.pdu_type = PduType::Response;
+    /// #     message.pdu.varbinds = vec![(arcs.into(), SnmpType::SnmpInteger(1))];
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..11 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 4, 1, 99999])
+    ///     .with_max_walk_vars(10);
+    /// match request.walk() {
+    ///     Err(SnmpError::WalkLimitExceeded(found)) => assert_eq!(found.len(), 10),
+    ///     other => panic!("expected the walk to be cut short, got {:?}", other),
+    /// }
+    /// ```
+    pub fn with_max_walk_vars(mut self, max_walk_vars: usize) -> Request {
+        self.max_walk_vars = max_walk_vars;
+        self
+    }
+
+    /// Sets the limits on what the parser accepts in a response. Defaults to `DecodeLimits::default()`.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::{DecodeLimits, PduType, SnmpError, SnmpType};
+    ///
+    /// // An agent that answers with far more variables than it was asked for.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = PduType::Response;
+    /// #     message.pdu.varbinds = (0..100).map(|i| (vec![1, 3, 6, 1, i].into(), SnmpType::SnmpNull)).collect();
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..1 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+    ///     .with_decode_limits(DecodeLimits { max_varbinds: 10, ..DecodeLimits::default() });
+    /// match request.send() {
+    ///     Err(SnmpError::LimitExceeded) => (),
+    ///     other => panic!("expected the limit to be exceeded, got {:?}", other),
+    /// }
+    /// ```
+    pub fn with_decode_limits(mut self, limits: DecodeLimits) -> Request {
+        self.decode_limits = limits;
+        self
+    }
+
+    /// Sends a SMTPv1 message and returns the reply or an error specifiying what went wrong.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                                  "public".to_owned(),
+    ///                                  vec![1, 3, 6, 1, 2, 1, 1, 1, 0]);
+    /// let message = request.send().unwrap();
+    /// let host = message.to_string().unwrap();
+    /// assert_eq!("Linux", &host[..5]);
+    /// ```
+    ///
+    /// Replies with another request ID, such as late replies to earlier requests, are thrown away
+    /// while waiting for the reply to this one.
+    /// ```
+    /// use std::net::UdpSocket;
+    /// use std::thread;
+    /// use rust_snmp::snmpv1::{CommunityMessage, Request};
+    /// use rust_snmp::types::{PduType, SnmpType};
+    ///
+    /// let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let address = agent.local_addr().unwrap().to_string();
+    /// thread::spawn(move || {
+    ///     let mut buf = [0; 1024];
+    ///     let (length, from) = agent.recv_from(&mut buf).unwrap();
+    ///     let mut message = CommunityMessage::decode(&buf[..length]).unwrap();
+    ///     message.pdu.pdu_type = PduType::Response;
+    ///     message.pdu.varbinds[0].1 = SnmpType::SnmpInteger(42);
+    ///     let mut stray = message.clone();
+    ///     stray.pdu.request_id = message.pdu.request_id.wrapping_add(1);
+    ///     stray.pdu.varbinds[0].1 = SnmpType::SnmpInteger(7);
+    ///     agent.send_to(&stray.encode(), from).unwrap();
+    ///     agent.send_to(&message.encode(), from).unwrap();
+    /// });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0]);
+    /// assert_eq!(request.send().unwrap().as_i64(), Some(42));
+    /// ```
+    pub fn send(&self) -> Result<Message, SnmpError> {
+        self.send_pdu(OutgoingPdu::new(self.version, SNMP_GET_REQUEST_CODE, &self.oids))
+    }
+
+    /// Sends the same message as `send` from the given socket, instead of binding a new one.
+    /// This lets tests answer from a socket of their own, and lets callers pick socket options.
+    ///
+    /// #Examples
+    /// ```
+    /// use std::net::UdpSocket;
+    /// use rust_snmp::snmpv1::Request;
+    ///
+    /// // The agent answers every request for sysDescr.0 with "Linux".
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     message.pdu.varbinds[0].1 = rust_snmp::types::SnmpType::SnmpOctetString(b"Linux".to_vec());
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..3 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1, 1, 0]);
+    /// for _ in 0..3 {
+    ///     let message = request.send_from(&socket).unwrap();
+    ///     assert_eq!(message.to_string().unwrap(), "Linux");
+    /// }
+    /// ```
+    pub fn send_from(&self, socket: &UdpSocket) -> Result<Message, SnmpError> {
+        let target = self.target()?;
+        self.send_pdu_on(socket, target, OutgoingPdu::new(self.version, SNMP_GET_REQUEST_CODE, &self.oids))
+    }
+
+    /// Sends a GetRequest for the OID with the settings of this request, instead of its own OIDs.
+    /// Every call uses a fresh request ID from `request_id_source`, so one request can be used
+    /// to poll an agent for many different OIDs.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    ///
+    /// // The agent echoes each request back as the response.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     message
+    /// # };
+    /// # let agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..2 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1, 1, 0]);
+    /// let uptime = request.get(&vec![1, 3, 6, 1, 2, 1, 1, 3, 0].into()).unwrap();
+    /// assert_eq!(uptime.oid().arcs(), &[1, 3, 6, 1, 2, 1, 1, 3, 0]);
+    /// let name = request.get(&"1.3.6.1.2.1.1.5.0".parse().unwrap()).unwrap();
+    /// assert_eq!(name.oid().arcs(), &[1, 3, 6, 1, 2, 1, 1, 5, 0]);
+    ///
+    /// # let requests = agent.join().unwrap();
+    /// # assert!(requests[0].pdu.request_id != requests[1].pdu.request_id);
+    /// ```
+    pub fn get(&self, oid: &Oid) -> Result<Message, SnmpError> {
+        let request = self.for_oids(vec![oid.clone()]);
+        request.send_pdu(OutgoingPdu::new(request.version, SNMP_GET_REQUEST_CODE, &request.oids))
+    }
+
+    /// Sends the same message as `send` without blocking, returning a future that resolves
+    /// to the reply. Requires the `async` feature and a tokio runtime.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// extern crate rust_snmp;
+    /// extern crate tokio;
+    /// use rust_snmp::snmpv1::Request;
+    /// # fn main() {
+    /// let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1, 1, 0]);
+    /// let message = runtime.block_on(request.send_async()).unwrap();
+    /// println!("{}", message.to_string().unwrap());
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn send_async(&self) -> impl Future<Output = Result<Message, SnmpError>> {
+        let sendpacket = validate_oids(&self.oids).and(match self.transport {
+            SnmpTransport::Udp => Ok(()),
+            SnmpTransport::Tcp => Err(SnmpError::Unsupported("requests over TCP can not be sent without blocking")),
+        }).map(|_| {
+            self.createpacket(OutgoingPdu::new(self.version, SNMP_GET_REQUEST_CODE, &self.oids))
+        });
+        let request_id = self.request_id;
+        let limits = self.decode_limits;
+        let community = if self.check_community { Some(self.community.clone()) } else { None };
+        let address = self.target.map_or_else(|| with_port(&self.address, self.port).into_owned(),
+                                               |target| target.to_string());
+        asynchronous::exchange(address, self.bind_addr, sendpacket, self.retry_policy(), self.max_message_size,
+                               move |reply| {
+            parse_reply(reply, request_id, community.as_ref().map(|community| &community[..]), &limits)
+        })
+    }
+
+    /// Sends a SNMPv1 GetNextRequest, asking for the value following each OID.
+    /// The OID that was actually returned is available through `Message::oid`.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1]);
+    /// let message = request.send_getnext().unwrap();
+    /// assert_eq!(message.oid().arcs(), &[1, 3, 6, 1, 2, 1, 1, 1, 0]);
+    /// ```
+    pub fn send_getnext(&self) -> Result<Message, SnmpError> {
+        self.send_pdu(OutgoingPdu::new(self.version, SNMP_GET_NEXT_REQUEST_CODE, &self.oids))
+    }
+
+    /// Sends a SNMPv1 SetRequest, asking the agent to assign the value to each OID.
+    /// If the agent rejects the value, a `SnmpError::ResponseError` with the error status is returned.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::SnmpType;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "private".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1, 6, 0]);
+    /// let location = SnmpType::SnmpString("Server room".to_owned());
+    /// request.set(location).unwrap();
+    /// ```
+    ///
+    /// Communities and values longer than 127 bytes are encoded with multi-byte lengths.
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::SnmpType;
+    ///
+    /// // The agent echoes the request back as the response.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     message
+    /// # };
+    /// # let agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..1 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, vec![b'c'; 200], vec![1, 3, 6, 1, 2, 1, 1, 6, 0]);
+    /// let location = "x".repeat(300);
+    /// let message = request.set(SnmpType::SnmpString(location.clone())).unwrap();
+    /// assert_eq!(message.to_string().unwrap(), location);
+    /// ```
+    pub fn set(&self, value: SnmpType) -> Result<Message, SnmpError> {
+        self.send_pdu(OutgoingPdu {
+            value: &value,
+            ..OutgoingPdu::new(self.version, SNMP_SET_REQUEST_CODE, &self.oids)
+        })
+    }
+
+    /// Sends a SNMPv2c InformRequest, regardless of the configured version, and waits for the
+    /// manager to acknowledge it. The notification is identified by `trap_oid` and carries the
+    /// uptime in hundredths of a second and the variable bindings. The OIDs of the request are not used.
+    /// Every inform is sent with a fresh request ID from `request_id_source`, so a late acknowledgement
+    /// of an earlier inform does not confirm this one.
+    /// Fails with `SnmpError::Timeout` if no acknowledgement arrives after all retries.
+    ///
+    /// #Examples
+    /// ```
+    /// use std::net::UdpSocket;
+    /// use std::thread;
+    /// use rust_snmp::snmpv1::{CommunityMessage, Request};
+    /// use rust_snmp::types::{PduType, SnmpError, SnmpType};
+    ///
+    /// let manager = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let address = manager.local_addr().unwrap().to_string();
+    /// let handle = thread::spawn(move || {
+    ///     let mut buf = [0; 1024];
+    ///     let (length, from) = manager.recv_from(&mut buf).unwrap();
+    ///     // Acknowledge the inform by echoing it back as a response.
+    ///     let mut message = CommunityMessage::decode(&buf[..length]).unwrap();
+    ///     assert_eq!(message.pdu.pdu_type, PduType::InformRequest);
+    ///     message.pdu.pdu_type = PduType::Response;
+    ///     manager.send_to(&message.encode(), from).unwrap();
+    ///     // Then answer the next one with the acknowledgement of the first.
+    ///     let (_, from) = manager.recv_from(&mut buf).unwrap();
+    ///     manager.send_to(&message.encode(), from).unwrap();
+    /// });
+    ///
+    /// let mut request = Request::new(address, "public", vec![1, 3, 6, 1]);
+    /// let varbinds = vec![(vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 1, 2].into(), SnmpType::SnmpInteger(2))];
+    /// let ack = request.send_inform(1500, vec![1, 3, 6, 1, 6, 3, 1, 1, 5, 3], &varbinds).unwrap();
+    /// assert_eq!(ack.varbinds()[0].1.as_timeticks(), Some(1500));
+    /// assert_eq!(ack.varbinds()[2].1.as_integer(), Some(2));
+    ///
+    /// // The manager only acknowledges the first inform again, which does not confirm the next.
+    /// request.timeout = 50;
+    /// match request.send_inform(1600, vec![1, 3, 6, 1, 6, 3, 1, 1, 5, 3], &varbinds) {
+    ///     Err(SnmpError::Timeout) => (),
+    ///     other => panic!("expected a timeout, got {:?}", other),
+    /// }
+    /// handle.join().unwrap();
+    /// ```
+    pub fn send_inform<O: Into<Oid>>(&self, uptime: u32, trap_oid: O,
+                                     varbinds: &[(Oid, SnmpType)]) -> Result<Message, SnmpError> {
+        let trap_oid = trap_oid.into();
+        trap_oid.validate()?;
+        for (oid, _) in varbinds {
+            oid.validate()?;
+        }
+
+        // The uptime and the notification OID always come first, as sysUpTime.0 and snmpTrapOID.0.
+        let mut encoded = Vec::with_capacity(40 + 20 * varbinds.len());
+        encode_varbind(&vec![1, 3, 6, 1, 2, 1, 1, 3, 0].into(), &SnmpType::SnmpTimeTicks(uptime), &mut encoded);
+        encode_varbind(&vec![1, 3, 6, 1, 6, 3, 1, 1, 4, 1, 0].into(), &SnmpType::SnmpObjectID(trap_oid),
+                       &mut encoded);
+        for (oid, value) in varbinds {
+            encode_varbind(oid, value, &mut encoded);
+        }
+
+        let request = self.for_oids(Vec::new());
+        let pdu = encode_pdu_varbinds(SNMP_INFORM_REQUEST_CODE, request.request_id, (0, 0), encoded);
+        let sendpacket = encode_message(SnmpVersion::V2c, &request.community, pdu);
+        request.send_packet(&sendpacket)
+    }
+
+    /// Sends a SNMPv2c GetBulkRequest, regardless of the configured version. The first `non_repeaters` OIDs are treated like a
+    /// GetNextRequest, while up to `max_repetitions` successors are returned for each of the rest.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 2]);
+    /// let message = request.get_bulk(0, 10).unwrap();
+    /// for &(ref oid, ref value) in message.varbinds() {
+    ///     println!("{:?} = {:?}", oid, value);
+    /// }
+    /// ```
+    pub fn get_bulk(&self, non_repeaters: u32, max_repetitions: u32) -> Result<Message, SnmpError> {
+        self.send_pdu(OutgoingPdu {
+            fields: (non_repeaters, max_repetitions),
+            ..OutgoingPdu::new(SnmpVersion::V2c, SNMP_GET_BULK_REQUEST_CODE, &self.oids)
+        })
+    }
+
+    /// Walks the subtree below each OID with repeated GetNextRequests,
+    /// returning every OID and value found in them in lexicographic order.
+    /// Table rows can then be put back together by the index at the end of each OID.
+    ///
+    /// Fails with `SnmpError::OidNotIncreasing` if the agent answers with an OID that does not come
+    /// after the one asked for, and with `SnmpError::WalkLimitExceeded` after `max_walk_vars` variables.
+    /// Both errors hold what was found before the walk stopped. A Report from the agent fails the walk
+    /// with `SnmpError::ReportError`, so a walk the agent refused does not look like an empty subtree.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1]);
+    /// for (oid, value) in request.walk().unwrap() {
+    ///     println!("{:?} = {:?}", oid, value);
+    /// }
+    /// ```
+    ///
+    /// Every step is sent with a fresh request ID from `request_id_source`, so a late or duplicated
+    /// reply to an earlier step is thrown away rather than taken for the current one.
+    /// ```
+    /// use std::net::UdpSocket;
+    /// use std::thread;
+    /// use rust_snmp::snmpv1::{CommunityMessage, Request};
+    /// use rust_snmp::types::{PduType, SnmpType};
+    ///
+    /// let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let address = agent.local_addr().unwrap().to_string();
+    /// thread::spawn(move || {
+    ///     let mut buf = [0; 1024];
+    ///     // Answer the first step with sysServices.0.
+    ///     let (length, from) = agent.recv_from(&mut buf).unwrap();
+    ///     let mut first = CommunityMessage::decode(&buf[..length]).unwrap();
+    ///     first.pdu.pdu_type = PduType::Response;
+    ///     first.pdu.varbinds = vec![(vec![1, 3, 6, 1, 2, 1, 1, 7, 0].into(), SnmpType::SnmpInteger(72))];
+    ///     agent.send_to(&first.encode(), from).unwrap();
+    ///     // Answer the second step with the first reply again, then with an OID past the subtree.
+    ///     let (length, from) = agent.recv_from(&mut buf).unwrap();
+    ///     agent.send_to(&first.encode(), from).unwrap();
+    ///     let mut second = CommunityMessage::decode(&buf[..length]).unwrap();
+    ///     second.pdu.pdu_type = PduType::Response;
+    ///     second.pdu.varbinds = vec![(vec![1, 3, 6, 1, 2, 1, 2, 1, 0].into(), SnmpType::SnmpInteger(2))];
+    ///     agent.send_to(&second.encode(), from).unwrap();
+    /// });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1]);
+    /// assert_eq!(request.walk().unwrap(),
+    ///            vec![(vec![1, 3, 6, 1, 2, 1, 1, 7, 0].into(), SnmpType::SnmpInteger(72))]);
+    /// ```
+    ///
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::SnmpError;
+    ///
+    /// // The agent answers the GetNextRequest with a Report.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Report;
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..1 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1]);
+    /// match request.walk() {
+    ///     Err(SnmpError::ReportError(_)) => (),
+    ///     other => panic!("expected ReportError, got {:?}", other),
+    /// }
+    /// ```
+    pub fn walk(&self) -> Result<Vec<(Oid, SnmpType)>, SnmpError> {
+        self.walk_with(|step, pdu| step.send_pdu(pdu))
+    }
+
+    /// Walks like `walk`, sending every step with `send`, which gets a copy of this request
+    /// with the OID and a fresh request ID for the step.
+    fn walk_with<F>(&self, send: F) -> Result<Vec<(Oid, SnmpType)>, SnmpError>
+        where F: Fn(&Request, OutgoingPdu) -> Result<Message, SnmpError> {
+        let mut results = Vec::new();
+        for root in &self.oids {
+            let mut current = root.clone();
+            loop {
+                let step = self.for_oids(vec![current.clone()]);
+                let pdu = OutgoingPdu::new(step.version, SNMP_GET_NEXT_REQUEST_CODE, &step.oids);
+                let message = match send(&step, pdu) {
+                    Ok(message) => message,
+                    // SNMPv1 agents signal the end of the MIB view with noSuchName.
+                    Err(SnmpError::ResponseError(SnmpResponseError::NoSuchName, _)) => break,
+                    Err(e) => return Err(e),
+                };
+                let message = reject_report(message)?;
+
+                // Stop at the end of the MIB view or once we leave the subtree.
+                let (oid, data) = message.varbinds.into_iter().next().ok_or(SnmpError::ParsingError)?;
+                if let SnmpType::EndOfMibView = data {
+                    break;
+                }
+                if !root.is_prefix_of(&oid) {
+                    break;
+                }
+                self.check_walk_step(&current, &oid, &mut results)?;
+                current = oid.clone();
+                results.push((oid, data));
+            }
+        }
+        Ok(sort_varbinds(results))
+    }
+
+    /// Walks the subtree below each OID with repeated SNMPv2c GetBulkRequests, regardless of the
+    /// configured version, returning every OID and value found in them in lexicographic order.
+    /// Each request asks for up to `max_repetitions` successors, which is kept between 1 and 100
+    /// so replies stay a sensible size. This needs far fewer round-trips than `walk` for large tables,
+    /// and fails the same way when the agent misbehaves. Like `walk`, it sends every step with a fresh
+    /// request ID, so a retransmitted reply to an earlier step is not taken for the current one.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv1::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "public".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 2, 2]);
+    /// for (oid, value) in request.bulk_walk(25).unwrap() {
+    ///     println!("{:?} = {:?}", oid, value);
+    /// }
+    /// ```
+    ///
+    /// Whatever a reply holds past the end of the subtree is left out.
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::{PduType, SnmpType};
+    ///
+    /// // The agent answers with the two indices of ifIndex, then runs on into ifDescr.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = PduType::Response;
+    /// #     message.pdu.error_index = 0;
+    /// #     message.pdu.varbinds = vec![
+    /// #         (vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 1, 1].into(), SnmpType::SnmpInteger(1)),
+    /// #         (vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 1, 2].into(), SnmpType::SnmpInteger(2)),
+    /// #         (vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 2, 1].into(), SnmpType::SnmpOctetString(b"lo".to_vec())),
+    /// #     ];
+    /// #     message
+    /// # };
+    /// # let agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..1 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 1]);
+    /// let indices: Vec<Option<i64>> = request.bulk_walk(25).unwrap().into_iter()
+    ///     .map(|(_, value)| value.as_integer()).collect();
+    /// assert_eq!(indices, vec![Some(1), Some(2)]);
+    /// ```
+    pub fn bulk_walk(&self, max_repetitions: u32) -> Result<Vec<(Oid, SnmpType)>, SnmpError> {
+        let max_repetitions = max_repetitions.clamp(1, SNMP_MAX_BULK_REPETITIONS);
+        let mut results = Vec::new();
+        for root in &self.oids {
+            let mut current = root.clone();
+            'walk: loop {
+                let step = self.for_oids(vec![current.clone()]);
+                let message = step.send_pdu(OutgoingPdu {
+                    fields: (0, max_repetitions),
+                    ..OutgoingPdu::new(SnmpVersion::V2c, SNMP_GET_BULK_REQUEST_CODE, &step.oids)
+                })?;
+                let message = reject_report(message)?;
+
+                // A reply may run past the end of the subtree, so check every OID in it.
+                // Stop at the end of the MIB view or once we leave the subtree.
+                for (oid, data) in message.varbinds {
+                    if let SnmpType::EndOfMibView = data {
+                        break 'walk;
+                    }
+                    if !root.is_prefix_of(&oid) {
+                        break 'walk;
+                    }
+                    self.check_walk_step(&current, &oid, &mut results)?;
+                    current = oid.clone();
+                    results.push((oid, data));
+                }
+            }
+        }
+        Ok(sort_varbinds(results))
+    }
+
+    /// Fails if the agent answered with an OID that does not come after the one asked for,
+    /// or if the walk has found as many variables as it may, handing over what was found.
+    fn check_walk_step(&self, previous: &Oid, oid: &Oid, results: &mut Vec<(Oid, SnmpType)>)
+                       -> Result<(), SnmpError> {
+        if oid <= previous {
+            debug!("the agent answered {} after {}, which does not increase", oid, previous);
+            return Err(SnmpError::OidNotIncreasing(sort_varbinds(mem::take(results))));
+        }
+        if results.len() >= self.max_walk_vars {
+            return Err(SnmpError::WalkLimitExceeded(sort_varbinds(mem::take(results))));
+        }
+        Ok(())
+    }
+
+    /// Writes the GetRequest `send` would send into the buffer instead of allocating it,
+    /// returning how many bytes were written. Useful where the packet is sent some other way,
+    /// such as on embedded targets that avoid the heap.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::SnmpError;
+    /// let mut request = Request::new("demo.snmplabs.com:161".to_owned(), "public",
+    ///                                vec![1, 3, 6, 1, 2, 1, 1, 5, 0]);
+    /// request.request_id = 1;
+    ///
+    /// let mut buf = [0; 64];
+    /// let length = request.write_packet(&mut buf).unwrap();
+    /// assert_eq!(length, 40);
+    /// assert_eq!(&buf[..13], &[0x30, 38, 0x02, 0x01, 0x00, 0x04, 0x06,
+    ///                          b'p', b'u', b'b', b'l', b'i', b'c']);
+    ///
+    /// match request.write_packet(&mut buf[..39]) {
+    ///     Err(SnmpError::BufferTooSmall) => (),
+    ///     other => panic!("expected BufferTooSmall, got {:?}", other),
+    /// }
+    /// ```
+    ///
+    /// OIDs are encoded the same way here as by `send`, whether they are scalars ending in `.0`,
+    /// have multi-byte arcs, or are long enough to need a multi-byte length.
+    /// ```
+    /// use std::net::UdpSocket;
+    /// use rust_snmp::snmpv1::Request;
+    ///
+    /// let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let address = agent.local_addr().unwrap().to_string();
+    /// let mut long = vec![1, 3];
+    /// long.extend(vec![1; 130]);
+    /// let mut long_encoded = vec![0x06, 0x81, 0x83, 0x2B];
+    /// long_encoded.extend(vec![0x01; 130]);
+    ///
+    /// for &(ref arcs, ref encoded) in &[
+    ///     (vec![1, 3, 6, 1, 2, 1, 1, 5, 0], vec![0x06, 0x08, 0x2B, 0x06, 0x01, 0x02, 0x01, 0x01, 0x05, 0x00]),
+    ///     (vec![1, 3, 6, 1, 4, 1, 2021, 100000],
+    ///      vec![0x06, 0x0A, 0x2B, 0x06, 0x01, 0x04, 0x01, 0x8F, 0x65, 0x86, 0x8D, 0x20]),
+    ///     (long, long_encoded),
+    /// ] {
+    ///     let mut request = Request::new(address.clone(), "public", arcs.clone());
+    ///     request.timeout = 1;
+    ///     let mut written = [0; 256];
+    ///     let length = request.write_packet(&mut written).unwrap();
+    ///
+    ///     // Nobody answers, but the agent sees what was sent.
+    ///     let _ = request.send();
+    ///     let mut sent = [0; 256];
+    ///     let (sent_length, _) = agent.recv_from(&mut sent).unwrap();
+    ///     assert_eq!(&written[..length], &sent[..sent_length]);
+    ///     assert!(sent[..sent_length].windows(encoded.len()).any(|w| w == &encoded[..]));
+    /// }
+    /// ```
+    pub fn write_packet(&self, buf: &mut [u8]) -> Result<usize, SnmpError> {
+        validate_oids(&self.oids)?;
+        let mut writer = BufferWriter::new(buf);
+        write_pdu(SNMP_GET_REQUEST_CODE, self.request_id, (0, 0), &self.oids,
+                  &SnmpType::SnmpNull, &mut writer)?;
+        self.community.write_snmp(&mut writer)?;                 // Community
+        (self.version as i64).write_snmp(&mut writer)?;          // SNMP version
+        writer.prepend_header(0x30, 0)?;                         // SNMP sequence
+        Ok(writer.finish())
+    }
+
+    /// Copies the settings of this request into a new one for the OIDs, with a fresh request ID.
+    fn for_oids(&self, oids: Vec<Oid>) -> Request {
+        Request {
+            address: self.address.clone(),
+            port: self.port,
+            oids: oids,
+            community: self.community.clone(),
+            version: self.version,
+            request_id: self.request_id_source.next(),
+            request_id_source: self.request_id_source,
+            timeout: self.timeout,
+            retries: self.retries,
+            check_community: self.check_community,
+            bind_addr: self.bind_addr,
+            jitter: self.jitter,
+            transport: self.transport,
+            max_message_size: self.max_message_size,
+            max_walk_vars: self.max_walk_vars,
+            decode_limits: self.decode_limits,
+            target: self.target,
+        }
+    }
+
+    fn send_pdu(&self, pdu: OutgoingPdu) -> Result<Message, SnmpError> {
+        validate_oids(pdu.oids)?;
+        let sendpacket = self.createpacket(pdu);
+        self.send_packet(&sendpacket)
+    }
+
+    /// Sends the packet over the transport of the request and waits for the reply.
+    fn send_packet(&self, sendpacket: &[u8]) -> Result<Message, SnmpError> {
+        let target = self.target()?;
+        match self.transport {
+            SnmpTransport::Udp => {
+                let socket = connect(self.bind_addr, &target)?;
+                self.send_packet_on(&socket, target, sendpacket)
+            },
+            SnmpTransport::Tcp => {
+                if self.bind_addr.is_some() {
+                    return Err(SnmpError::Unsupported("requests over TCP can not be sent from a chosen address"));
+                }
+                if let Some(jitter) = self.jitter {
+                    thread::sleep(random_delay(jitter));
+                }
+                let receivepacket = exchange_tcp(target, sendpacket, self.timeout, self.max_message_size)?;
+                let community = if self.check_community { Some(&self.community[..]) } else { None };
+                parse_reply(&receivepacket, self.request_id, community, &self.decode_limits)
+            },
+        }
+    }
+
+    fn send_pdu_on(&self, socket: &UdpSocket, target: SocketAddr, pdu: OutgoingPdu) -> Result<Message, SnmpError> {
+        // Create packet
+        validate_oids(pdu.oids)?;
+        let sendpacket = self.createpacket(pdu);
+        self.send_packet_on(socket, target, &sendpacket)
+    }
+
+    fn send_packet_on(&self, socket: &UdpSocket, target: SocketAddr, sendpacket: &[u8]) -> Result<Message, SnmpError> {
+        // Send the packet and wait for a reply
+        let mut receivepacket = receive_buffer(self.max_message_size);
+        let community = if self.check_community { Some(&self.community[..]) } else { None };
+        exchange(socket, target, sendpacket, self.retry_policy(), &mut receivepacket,
+                 |reply| parse_reply(reply, self.request_id, community, &self.decode_limits))
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy { timeout: self.timeout, retries: self.retries, jitter: self.jitter }
+    }
+
+    /// Builds the packet.
+    fn createpacket(&self, pdu: OutgoingPdu) -> Vec<u8> {
+        let message = CommunityMessage {
+            version: pdu.version,
+            community: self.community.clone(),
+            pdu: Pdu {
+                pdu_type: pdu.pdu_type.into(),
+                request_id: self.request_id,
+                error_status: pdu.fields.0 as i64,
+                error_index: pdu.fields.1 as i64,
+                varbinds: pdu.oids.iter().map(|oid| (oid.clone(), pdu.value.clone())).collect(),
+            },
+        };
+        message.encode()
+    }
+}
+
+/// What a request puts in the PDU it sends, apart from the request ID and community.
+#[derive(Debug, Clone, Copy)]
+struct OutgoingPdu<'a> {
+    version: SnmpVersion,
+    pdu_type: u8,
+    /// The error status and index, which GetBulkRequests reuse for the non-repeaters and max-repetitions.
+    fields: (u32, u32),
+    oids: &'a [Oid],
+    /// The value sent along with every OID.
+    value: &'a SnmpType,
+}
+
+impl<'a> OutgoingPdu<'a> {
+    /// Asks for the OIDs in a PDU of the type, with Null values and no error status or index.
+    fn new(version: SnmpVersion, pdu_type: u8, oids: &'a [Oid]) -> OutgoingPdu<'a> {
+        OutgoingPdu {
+            version: version,
+            pdu_type: pdu_type,
+            fields: (0, 0),
+            oids: oids,
+            value: &SnmpType::SnmpNull,
+        }
+    }
+}
+
+/// A SNMPv1 trap, which an agent sends unprompted to tell a manager about an event.
+///
+/// #Examples
+/// ```
+/// use std::net::{Ipv4Addr, UdpSocket};
+/// use rust_snmp::snmpv1::{send_trap, Trap};
+/// use rust_snmp::types::{GenericTrap, SnmpType};
+///
+/// let manager = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let address = manager.local_addr().unwrap().to_string();
+///
+/// let trap = Trap {
+///     enterprise: vec![1, 3, 6, 1, 4, 1, 2021].into(),
+///     agent_address: Ipv4Addr::new(192, 168, 0, 1),
+///     generic_trap: GenericTrap::EnterpriseSpecific,
+///     specific_trap: 42,
+///     timestamp: 1500,
+///     varbinds: vec![(vec![1, 3, 6, 1, 4, 1, 2021, 1].into(), SnmpType::SnmpInteger(7))],
+/// };
+/// send_trap(&address, "public", &trap).unwrap();
+///
+/// let mut buf = [0; 1024];
+/// let (length, _) = manager.recv_from(&mut buf).unwrap();
+/// assert_eq!(buf[2 + 3 + 2 + b"public".len()], 0xA4);
+/// assert_eq!(&buf[length - 3..length], &[0x02, 0x01, 7]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Trap {
+    /// The OID of the kind of object that sent the trap.
+    pub enterprise: Oid,
+    /// The address of the agent that sent the trap.
+    pub agent_address: Ipv4Addr,
+    /// What kind of event the trap is about.
+    pub generic_trap: GenericTrap,
+    /// The enterprise-specific event, when `generic_trap` is `EnterpriseSpecific`.
+    pub specific_trap: i64,
+    /// How long the agent had been up when the trap was sent, in hundredths of a second.
+    pub timestamp: u32,
+    /// The OIDs and values the trap carries.
+    pub varbinds: Vec<(Oid, SnmpType)>,
+}
+
+impl Trap {
+    /// Builds the SNMPv1 message carrying the trap.
+    fn createpacket(&self, community: &[u8]) -> Vec<u8> {
+        let mut varbinds = Vec::with_capacity(20 * self.varbinds.len());
+        for (oid, value) in &self.varbinds {
+            encode_varbind(oid, value, &mut varbinds);
+        }
+
+        // PDU contents
+        let mut pdu = Vec::with_capacity(32 + varbinds.len());
+        pdu.append(&mut self.enterprise.encode_snmp());
+        pdu.append(&mut self.agent_address.encode_snmp());
+        pdu.append(&mut (self.generic_trap as i64).encode_snmp());
+        pdu.append(&mut self.specific_trap.encode_snmp());
+        pdu.append(&mut TimeTicks(self.timestamp).encode_snmp());
+        pdu.push(0x30);                                    // Start of sequence
+        write_length(varbinds.len(), &mut pdu);            // Size
+        pdu.append(&mut varbinds);
+
+        let mut buf = Vec::with_capacity(4 + pdu.len());
+        buf.push(SNMP_TRAP_CODE);
+        write_length(pdu.len(), &mut buf);
+        buf.append(&mut pdu);
+        encode_message(SnmpVersion::V1, community, buf)
+    }
+}
+
+/// Parses a trap message, returning the trap and the community it was sent with.
+fn parse_trap(packet: &[u8], limits: &DecodeLimits) -> Result<(Trap, Vec<u8>), SnmpError> {
+    let mut iterator = packet.iter();
+
+    // Confirm that the first bit is the SNMP flag.
+    if next_byte(&mut iterator)? != 0x30 {
+        return Err(SnmpError::ParsingError);
+    }
+
+    read_message_length(&mut iterator)?;
+
+    // Traps with their own PDU only exist in SNMPv1.
+    match extract_value(&mut iterator)? {
+        SnmpType::SnmpInteger(i) if i == SnmpVersion::V1 as i64 => (),
+        _ => return Err(SnmpError::ParsingError),
+    };
+
+    let community = match extract_octets(&mut iterator) {
+        Ok(community) => community,
+        Err(SnmpError::InvalidType) => return Err(SnmpError::ParsingError),
+        Err(e) => return Err(e),
+    };
+
+    if next_byte(&mut iterator)? != SNMP_TRAP_CODE {
+        return Err(SnmpError::ParsingError);
+    }
+    read_contents_length(&mut iterator)?;
+
+    let enterprise = match extract_value(&mut iterator)? {
+        SnmpType::SnmpObjectID(oid) => oid,
+        _ => return Err(SnmpError::ParsingError),
+    };
+    let agent_address = match extract_value(&mut iterator)? {
+        SnmpType::SnmpIpAddress(ip) => ip,
+        _ => return Err(SnmpError::ParsingError),
+    };
+    let generic_trap = match extract_value(&mut iterator)? {
+        SnmpType::SnmpInteger(0) => GenericTrap::ColdStart,
+        SnmpType::SnmpInteger(1) => GenericTrap::WarmStart,
+        SnmpType::SnmpInteger(2) => GenericTrap::LinkDown,
+        SnmpType::SnmpInteger(3) => GenericTrap::LinkUp,
+        SnmpType::SnmpInteger(4) => GenericTrap::AuthenticationFailure,
+        SnmpType::SnmpInteger(5) => GenericTrap::EgpNeighborLoss,
+        SnmpType::SnmpInteger(6) => GenericTrap::EnterpriseSpecific,
+        _ => return Err(SnmpError::ParsingError),
+    };
+    let specific_trap = match extract_value(&mut iterator)? {
+        SnmpType::SnmpInteger(i) => i,
+        _ => return Err(SnmpError::ParsingError),
+    };
+    let timestamp = match extract_value(&mut iterator)? {
+        SnmpType::SnmpTimeTicks(t) => t,
+        _ => return Err(SnmpError::ParsingError),
+    };
+    let varbinds = parse_varbinds(&mut iterator, limits)?;
+
+    let trap = Trap {
+        enterprise: enterprise,
+        agent_address: agent_address,
+        generic_trap: generic_trap,
+        specific_trap: specific_trap,
+        timestamp: timestamp,
+        varbinds: varbinds,
+    };
+    Ok((trap, community))
+}
+
+/// Sends a SNMPv1 GetRequest for the OID, written in dotted notation, and returns the value
+/// the agent answers with. Use `Request` for anything beyond a one-off query.
+///
+/// #Examples
+/// ```
+/// // The agent echoes the request back as a response, with its Null value.
+/// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+/// # let address = agent_socket.local_addr().unwrap().to_string();
+/// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+/// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+/// #     message
+/// # };
+/// # let _agent = std::thread::spawn(move || {
+/// #     let mut buf = [0; 65535];
+/// #     let mut requests = Vec::new();
+/// #     for _ in 0..1 {
+/// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+/// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+/// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+/// #         requests.push(message);
+/// #     }
+/// #     requests
+/// # });
+///
+/// let value = rust_snmp::snmpv1::get(&address, "public", "1.3.6.1.2.1.1.5.0").unwrap();
+/// assert!(value.is_null());
+/// assert!(rust_snmp::snmpv1::get(&address, "public", "not an oid").is_err());
+/// ```
+pub fn get(address: &str, community: &str, oid: &str) -> Result<SnmpType, SnmpError> {
+    let oid: Oid = oid.parse()?;
+    let request = Request::new(address.to_owned(), community, oid);
+    let message = request.send()?;
+    Ok(message.varbinds.into_iter().next().ok_or(SnmpError::ParsingError)?.1)
+}
+
+/// Sends the trap to the manager at the address, on port 162 unless the address names one.
+/// Traps are not acknowledged, so this returns as soon as the trap is sent.
+pub fn send_trap<C: Into<Vec<u8>>>(address: &str, community: C, trap: &Trap) -> Result<(), SnmpError> {
+    trap.enterprise.validate()?;
+    for (oid, _) in &trap.varbinds {
+        oid.validate()?;
+    }
+    let target = resolve(&with_port(address, DEFAULT_TRAP_PORT))?;
+    let socket = bind(None, &target)?;
+    socket.send_to(&trap.createpacket(&community.into()), target)?;
+    Ok(())
+}
+
+/// Sends the requests without blocking, with no more than `concurrency` of them waiting for a reply
+/// at a time, and returns a future that resolves to their replies in the order of the requests.
+/// Each request keeps its own timeout and retries, which start once it is sent, and one failing
+/// does not affect the others. Requires the `async` feature and a tokio runtime.
+///
+/// #Examples
+/// ```
+/// extern crate rust_snmp;
+/// extern crate tokio;
+/// use rust_snmp::snmpv1::{poll_all, Request};
+/// # fn main() {
+/// // The agent echoes each request back as the response.
+/// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+/// # let address = agent_socket.local_addr().unwrap().to_string();
+/// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+/// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+/// #     message
+/// # };
+/// # let _agent = std::thread::spawn(move || {
+/// #     let mut buf = [0; 65535];
+/// #     let mut requests = Vec::new();
+/// #     for _ in 0..10 {
+/// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+/// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+/// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+/// #         requests.push(message);
+/// #     }
+/// #     requests
+/// # });
+///
+/// let requests: Vec<Request> = (1..11).map(|column| {
+///     Request::new(address.clone(), "public", vec![1, 3, 6, 1, 2, 1, 2, 2, 1, column, 1])
+/// }).collect();
+/// let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// let replies = runtime.block_on(poll_all(&requests, 3));
+/// assert_eq!(replies.len(), 10);
+/// for (request, reply) in requests.iter().zip(replies) {
+///     assert_eq!(reply.unwrap().oid(), &request.oids[0]);
+/// }
+/// # }
+/// ```
+#[cfg(feature = "async")]
+pub fn poll_all(requests: &[Request], concurrency: usize) -> impl Future<Output = Vec<Result<Message, SnmpError>>> {
+    let replies = requests.iter().map(|request| request.send_async()).collect();
+    asynchronous::join_limited(replies, concurrency)
+}
+
+/// Builds a `Request` step by step, for when the defaults of `Request::new` do not fit.
+///
+/// #Examples
+/// ```
+/// use std::time::Duration;
+/// use rust_snmp::snmpv1::RequestBuilder;
+/// use rust_snmp::types::SnmpError;
+/// let request = RequestBuilder::new()
+///     .address("demo.snmplabs.com:161")
+///     .community("public")
+///     .oid(vec![1, 3, 6, 1, 2, 1, 1, 1, 0])
+///     .oid(vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+///     .timeout(5000)
+///     .request_id(42)
+///     .retries(2)
+///     .jitter(Duration::from_millis(200))
+///     .build()
+///     .unwrap();
+/// assert_eq!(request.oids.len(), 2);
+/// assert_eq!(request.jitter, Some(Duration::from_millis(200)));
+/// assert_eq!(request.request_id, 42);
+///
+/// // The address, community and at least one OID are required.
+/// match RequestBuilder::new().address("demo.snmplabs.com:161").build() {
+///     Err(SnmpError::InvalidRequest(reason)) => assert_eq!(reason, "the request needs a community"),
+///     other => panic!("expected the request to be invalid, got {:?}", other),
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RequestBuilder {
+    address: Option<String>,
+    port: Option<u16>,
+    target: Option<SocketAddr>,
+    community: Option<Vec<u8>>,
+    oids: Vec<Oid>,
+    version: Option<SnmpVersion>,
+    request_id: Option<u32>,
+    request_id_source: Option<RequestIdSource>,
+    timeout: Option<u64>,
+    retries: Option<u32>,
+    jitter: Option<time::Duration>,
+    transport: Option<SnmpTransport>,
+    max_message_size: Option<usize>,
+    max_walk_vars: Option<usize>,
+    decode_limits: Option<DecodeLimits>,
+}
+
+impl RequestBuilder {
+    /// Creates a builder with nothing set.
+    pub fn new() -> RequestBuilder {
+        RequestBuilder::default()
+    }
+
+    /// Sets the address to send the request to.
+    pub fn address<A: Into<String>>(mut self, address: A) -> RequestBuilder {
+        self.address = Some(address.into());
+        self
+    }
+
+    /// Sets the port to send the request to if the address does not name one. Defaults to 161.
+    pub fn port(mut self, port: u16) -> RequestBuilder {
+        self.port = Some(port);
+        self
+    }
+
+    /// Sets the address to send the request to as one that needs no resolving,
+    /// instead of `address`.
+    pub fn target(mut self, target: SocketAddr) -> RequestBuilder {
+        self.target = Some(target);
+        self
+    }
+
+    /// Sets the community used to authenticate.
+    pub fn community<C: Into<Vec<u8>>>(mut self, community: C) -> RequestBuilder {
+        self.community = Some(community.into());
+        self
+    }
+
+    /// Adds an OID to ask for. Can be called several times to ask for several OIDs.
+    pub fn oid<O: Into<Oid>>(mut self, oid: O) -> RequestBuilder {
+        self.oids.push(oid.into());
+        self
+    }
+
+    /// Sets the SNMP version to send the request as. Defaults to SNMPv1.
+    pub fn version(mut self, version: SnmpVersion) -> RequestBuilder {
+        self.version = Some(version);
+        self
+    }
+
+    /// Sets the request ID. Defaults to a random number.
+    pub fn request_id(mut self, request_id: u32) -> RequestBuilder {
+        self.request_id = Some(request_id);
+        self
+    }
+
+    /// Sets where request IDs come from, both for the request and for those sent through
+    /// `Request::get`. Defaults to random numbers.
+    pub fn request_id_source(mut self, source: RequestIdSource) -> RequestBuilder {
+        self.request_id_source = Some(source);
+        self
+    }
+
+    /// Sets how long to wait for a reply, in milliseconds. Defaults to 1000ms.
+    pub fn timeout(mut self, timeout: u64) -> RequestBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how many times to resend the request if no reply arrives. Defaults to 0.
+    pub fn retries(mut self, retries: u32) -> RequestBuilder {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// Sets the longest random delay to wait before sending and before every retry. Defaults to no delay.
+    pub fn jitter(mut self, jitter: time::Duration) -> RequestBuilder {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// Sets the transport to send the request over. Defaults to UDP.
+    pub fn transport(mut self, transport: SnmpTransport) -> RequestBuilder {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Sets the largest response to accept, in bytes. Defaults to `DEFAULT_MAX_MESSAGE_SIZE`.
+    pub fn max_message_size(mut self, max_message_size: usize) -> RequestBuilder {
+        self.max_message_size = Some(max_message_size);
+        self
+    }
+
+    /// Sets the most variables a walk returns. Defaults to `DEFAULT_MAX_WALK_VARS`.
+    pub fn max_walk_vars(mut self, max_walk_vars: usize) -> RequestBuilder {
+        self.max_walk_vars = Some(max_walk_vars);
+        self
+    }
+
+    /// Sets the limits on what the parser accepts in a response. Defaults to `DecodeLimits::default()`.
+    pub fn decode_limits(mut self, limits: DecodeLimits) -> RequestBuilder {
+        self.decode_limits = Some(limits);
+        self
+    }
+
+    /// Builds the request, or returns an error if the address, community or OIDs are missing.
+    pub fn build(self) -> Result<Request, SnmpError> {
+        let address = match (self.address, self.target) {
+            (Some(address), _) => address,
+            (None, Some(target)) => target.to_string(),
+            (None, None) => return Err(SnmpError::InvalidRequest("the request needs an address")),
+        };
+        let community = self.community.ok_or(SnmpError::InvalidRequest("the request needs a community"))?;
+        if self.oids.is_empty() {
+            return Err(SnmpError::InvalidRequest("the request needs at least one OID"));
+        }
+
+        let mut request = Request::from_oids(address, community, self.oids);
+        if let Some(port) = self.port {
+            request.port = port;
+        }
+        if let Some(version) = self.version {
+            request.version = version;
+        }
+        if let Some(source) = self.request_id_source {
+            request.request_id_source = source;
+            request.request_id = source.next();
+        }
+        if let Some(request_id) = self.request_id {
+            request.request_id = request_id;
+        }
+        if let Some(timeout) = self.timeout {
+            request.timeout = timeout;
+        }
+        if let Some(retries) = self.retries {
+            request.retries = retries;
+        }
+        if let Some(transport) = self.transport {
+            request.transport = transport;
+        }
+        if let Some(max_message_size) = self.max_message_size {
+            request.max_message_size = max_message_size;
+        }
+        if let Some(max_walk_vars) = self.max_walk_vars {
+            request.max_walk_vars = max_walk_vars;
+        }
+        if let Some(limits) = self.decode_limits {
+            request.decode_limits = limits;
+        }
+        request.jitter = self.jitter;
+        request.target = self.target;
+        Ok(request)
+    }
+}
+
+
+/// Keeps a single UDP socket open to send many requests from, along with
+/// the settings to send them with.
+///
+/// The session owns its socket, which is closed when the session is dropped or `close`d.
+/// Sessions are not `Clone`, so a socket is never shared by accident; `try_clone` shares it
+/// on purpose, by duplicating its file descriptor.
+///
+/// Requests sent at the same time from one socket, whether from clones or from threads sharing
+/// a `&SnmpSession`, may receive and throw away each other's replies, and then time out.
+/// Give every thread that sends requests at the same time a session of its own.
+///
+/// #Examples
+/// ```no_run
+/// use rust_snmp::snmpv1::SnmpSession;
+/// let session = SnmpSession::new("public".to_owned()).unwrap();
+/// for _ in 0..10 {
+///     let message = session.get("demo.snmplabs.com:161", vec![1, 3, 6, 1, 2, 1, 1, 3, 0]).unwrap();
+///     println!("{:?}", message.to_duration().unwrap());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SnmpSession {
+    socket: UdpSocket,
+    bind_addr: Option<SocketAddr>,
+    /// The community used to authenticate, which may be any bytes.
+    pub community: Vec<u8>,
+    /// The SNMP version to send requests as.
+    pub version: SnmpVersion,
+    /// How long to wait for a reply, in milliseconds.
+    pub timeout: u64,
+    /// How many times to resend a request if no reply arrives.
+    /// The timeout is doubled for every retry.
+    pub retries: u32,
+    /// Where the request IDs of the requests come from.
+    pub request_id_source: RequestIdSource,
+    /// The port to send requests to when their address does not name one.
+    pub port: u16,
+}
+
+impl SnmpSession {
+    /// Binds the socket of the session to any address.
+    /// Defaults version to SNMPv1, timeout to 1000ms, retries to 0, request IDs to sequential
+    /// and the port to 161.
+    pub fn new<C: Into<Vec<u8>>>(community: C) -> Result<SnmpSession, SnmpError> {
+        SnmpSession::bind(community, None)
+    }
+
+    /// Binds the socket of the session to the given local address, or any IPv4 address if `None`.
+    /// Bind to `[::]:0` to reach IPv6 agents.
+    pub fn bind<C: Into<Vec<u8>>>(community: C, bind_addr: Option<SocketAddr>) -> Result<SnmpSession, SnmpError> {
+        Ok(SnmpSession {
+            socket: bind_session(bind_addr)?,
+            bind_addr: bind_addr,
+            community: community.into(),
+            version: SnmpVersion::V1,
+            timeout: 1000,
+            retries: 0,
+            request_id_source: RequestIdSource::Sequential,
+            port: DEFAULT_PORT,
+        })
+    }
+
+    /// Returns the local address the socket of the session is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr, SnmpError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Closes the socket of the session. Dropping the session does the same,
+    /// but this makes the point where the socket is released clear.
+    pub fn close(self) {
+        drop(self);
+    }
+
+    /// Closes the socket and binds a new one to the same local address, such as after
+    /// the network changed. Late replies to requests sent from the old socket are lost.
+    /// If the new socket can not be bound, the session is left with one bound to any address.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::SnmpSession;
+    /// let mut session = SnmpSession::bind("public", Some("127.0.0.1:0".parse().unwrap())).unwrap();
+    /// let before = session.local_addr().unwrap();
+    /// session.reconnect().unwrap();
+    /// let after = session.local_addr().unwrap();
+    /// assert_eq!(after.ip(), before.ip());
+    /// session.close();
+    /// ```
+    pub fn reconnect(&mut self) -> Result<(), SnmpError> {
+        // The old socket has to go before a new one can take its port.
+        let any = bind_session(None)?;
+        drop(mem::replace(&mut self.socket, any));
+        self.socket = bind_session(self.bind_addr)?;
+        Ok(())
+    }
+
+    /// Creates a second session with the same settings that shares the socket of this one,
+    /// by duplicating its file descriptor. The socket stays open until both are closed.
+    pub fn try_clone(&self) -> Result<SnmpSession, SnmpError> {
+        Ok(SnmpSession {
+            socket: self.socket.try_clone()?,
+            bind_addr: self.bind_addr,
+            community: self.community.clone(),
+            version: self.version,
+            timeout: self.timeout,
+            retries: self.retries,
+            request_id_source: self.request_id_source,
+            port: self.port,
+        })
+    }
+
+    /// Sends a GetRequest for the OID to the agent at the address. Late replies to earlier requests
+    /// carry another request ID, and are thrown away while waiting for the reply to this one.
+    ///
+    /// #Examples
+    /// ```
+    /// use std::net::UdpSocket;
+    /// use std::thread;
+    /// use rust_snmp::snmpv1::{CommunityMessage, SnmpSession};
+    /// use rust_snmp::types::{PduType, SnmpType};
+    ///
+    /// let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let address = agent.local_addr().unwrap().to_string();
+    /// thread::spawn(move || {
+    ///     let mut buf = [0; 1024];
+    ///     // Answer the first request twice, as if the reply had been duplicated, and the second once.
+    ///     for (value, copies) in vec![(1, 2), (2, 1)] {
+    ///         let (length, from) = agent.recv_from(&mut buf).unwrap();
+    ///         let mut message = CommunityMessage::decode(&buf[..length]).unwrap();
+    ///         message.pdu.pdu_type = PduType::Response;
+    ///         message.pdu.varbinds[0].1 = SnmpType::SnmpInteger(value);
+    ///         for _ in 0..copies {
+    ///             agent.send_to(&message.encode(), from).unwrap();
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// let session = SnmpSession::new("public").unwrap();
+    /// assert_eq!(session.get(&address, vec![1, 3, 6, 1, 2, 1, 1, 7, 0]).unwrap().as_i64(), Some(1));
+    /// assert_eq!(session.get(&address, vec![1, 3, 6, 1, 2, 1, 1, 7, 0]).unwrap().as_i64(), Some(2));
+    /// ```
+    pub fn get<O: Into<Oid>>(&self, address: &str, oid: O) -> Result<Message, SnmpError> {
+        self.send_pdu(address, &self.community, SNMP_GET_REQUEST_CODE, oid.into(), &SnmpType::SnmpNull)
+    }
+
+    /// Sends a GetNextRequest for the OID to the agent at the address.
+    pub fn get_next<O: Into<Oid>>(&self, address: &str, oid: O) -> Result<Message, SnmpError> {
+        self.send_pdu(address, &self.community, SNMP_GET_NEXT_REQUEST_CODE, oid.into(), &SnmpType::SnmpNull)
+    }
+
+    /// Sends a SetRequest assigning the value to the OID to the agent at the address.
+    pub fn set<O: Into<Oid>>(&self, address: &str, oid: O, value: SnmpType) -> Result<Message, SnmpError> {
+        self.send_pdu(address, &self.community, SNMP_SET_REQUEST_CODE, oid.into(), &value)
+    }
+
+    /// Walks the subtree below the OID at the agent at the address with GetNextRequests sent from
+    /// the socket of the session, returning every OID and value in it in lexicographic order.
+    /// Fails the same way as `Request::walk` when the agent misbehaves.
+    ///
+    /// #Examples
+    /// ```
+    /// use std::net::UdpSocket;
+    /// use std::thread;
+    /// use rust_snmp::snmpv1::{CommunityMessage, SnmpSession};
+    /// use rust_snmp::types::{PduType, SnmpType};
+    ///
+    /// // The agent answers with sysDescr.0 and sysObjectID.0, then with an OID past the subtree.
+    /// let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// let address = agent.local_addr().unwrap().to_string();
+    /// thread::spawn(move || {
+    ///     let mut buf = [0; 1024];
+    ///     for &(ref arcs, value) in &[(vec![1, 3, 6, 1, 2, 1, 1, 1, 0], 1),
+    ///                                 (vec![1, 3, 6, 1, 2, 1, 1, 2, 0], 2),
+    ///                                 (vec![1, 3, 6, 1, 2, 1, 2, 1, 0], 3)] {
+    ///         let (length, from) = agent.recv_from(&mut buf).unwrap();
+    ///         let mut message = CommunityMessage::decode(&buf[..length]).unwrap();
+    ///         message.pdu.pdu_type = PduType::Response;
+    ///         message.pdu.varbinds = vec![(arcs.clone().into(), SnmpType::SnmpInteger(value))];
+    ///         agent.send_to(&message.encode(), from).unwrap();
+    ///     }
+    /// });
+    ///
+    /// let session = SnmpSession::new("public").unwrap();
+    /// let values: Vec<Option<i64>> = session.walk(&address, vec![1, 3, 6, 1, 2, 1, 1]).unwrap()
+    ///     .into_iter().map(|(_, value)| value.as_integer()).collect();
+    /// assert_eq!(values, vec![Some(1), Some(2)]);
+    /// ```
+    pub fn walk<O: Into<Oid>>(&self, address: &str, oid: O) -> Result<Vec<(Oid, SnmpType)>, SnmpError> {
+        let request = self.request(address, &self.community, oid.into());
+        let target = resolve(&with_port(address, self.port))?;
+        request.walk_with(|step, pdu| step.send_pdu_on(&self.socket, target, pdu))
+    }
+
+    /// Sends a GetRequest for the OID to the agent at the address, with the community instead of
+    /// the one of the session. This lets one session poll agents that each have their own community.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::SnmpSession;
+    ///
+    /// // The agent echoes the request back as the response.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..1 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let session = SnmpSession::new("public").unwrap();
+    /// let message = session.get_with_community(&address, "tenant", vec![1, 3, 6, 1, 2, 1, 1, 5, 0]).unwrap();
+    /// assert_eq!(message.community(), b"tenant");
+    /// assert_eq!(session.community, b"public");
+    /// ```
+    pub fn get_with_community<C, O>(&self, address: &str, community: C, oid: O) -> Result<Message, SnmpError>
+        where C: AsRef<[u8]>, O: Into<Oid> {
+        self.send_pdu(address, community.as_ref(), SNMP_GET_REQUEST_CODE, oid.into(), &SnmpType::SnmpNull)
+    }
+
+    /// Sends a GetNextRequest for the OID to the agent at the address, with the community instead of
+    /// the one of the session.
+    pub fn get_next_with_community<C, O>(&self, address: &str, community: C, oid: O) -> Result<Message, SnmpError>
+        where C: AsRef<[u8]>, O: Into<Oid> {
+        self.send_pdu(address, community.as_ref(), SNMP_GET_NEXT_REQUEST_CODE, oid.into(), &SnmpType::SnmpNull)
+    }
+
+    /// Sends a SetRequest assigning the value to the OID to the agent at the address, with the community
+    /// instead of the one of the session. Agents usually have a separate community for writing.
+    pub fn set_with_community<C, O>(&self, address: &str, community: C, oid: O,
+                                    value: SnmpType) -> Result<Message, SnmpError>
+        where C: AsRef<[u8]>, O: Into<Oid> {
+        self.send_pdu(address, community.as_ref(), SNMP_SET_REQUEST_CODE, oid.into(), &value)
+    }
+
+    fn send_pdu(&self, address: &str, community: &[u8], pdu_type: u8, oid: Oid,
+                value: &SnmpType) -> Result<Message, SnmpError> {
+        let request = self.request(address, community, oid);
+        let target = resolve(&with_port(address, self.port))?;
+        request.send_pdu_on(&self.socket, target,
+                            OutgoingPdu { value: value, ..OutgoingPdu::new(self.version, pdu_type, &request.oids) })
+    }
+
+    /// Creates a request for the OID with the settings of the session.
+    fn request(&self, address: &str, community: &[u8], oid: Oid) -> Request {
+        let mut request = Request::new(address, community, oid);
+        request.version = self.version;
+        request.request_id = self.request_id_source.next();
+        request.request_id_source = self.request_id_source;
+        request.timeout = self.timeout;
+        request.retries = self.retries;
+        request
+    }
+
+    /// Sends the bytes to the agent at the address as they are, and parses the reply as any other.
+    /// This helps reproduce quirks of agents with PDUs the other methods do not build.
+    /// The reply must carry the request ID of the bytes if they are a message this library can read,
+    /// and any request ID otherwise. The community of the reply is not checked.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::{CommunityMessage, Pdu, SnmpSession};
+    /// use rust_snmp::types::{PduType, SnmpType, SnmpVersion};
+    ///
+    /// // The agent echoes the request back as the response.
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..1 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// // A GetRequest with an error index, which no other method sends.
+    /// let mut pdu = Pdu::new(PduType::GetRequest, 42, vec![(vec![1, 3, 6, 1, 2, 1, 1, 5, 0].into(), SnmpType::SnmpNull)]);
+    /// pdu.error_index = 1;
+    /// let message = CommunityMessage { version: SnmpVersion::V2c, community: b"public".to_vec(), pdu: pdu };
+    ///
+    /// let session = SnmpSession::new("public").unwrap();
+    /// let reply = session.send_raw(&address, &message.encode()).unwrap();
+    /// assert_eq!(reply.oid().arcs(), &[1, 3, 6, 1, 2, 1, 1, 5, 0]);
+    /// ```
+    pub fn send_raw(&self, address: &str, bytes: &[u8]) -> Result<Message, SnmpError> {
+        let target = resolve(&with_port(address, self.port))?;
+        let mut receivepacket = receive_buffer(DEFAULT_MAX_MESSAGE_SIZE);
+        let request_id = CommunityMessage::decode(bytes).map(|message| message.pdu.request_id).ok();
+        let policy = RetryPolicy { timeout: self.timeout, retries: self.retries, jitter: None };
+        exchange(&self.socket, target, bytes, policy, &mut receivepacket, |reply| {
+            let request_id = match request_id {
+                Some(request_id) => request_id,
+                None => CommunityMessage::decode(reply)?.pdu.request_id,
+            };
+            parse_reply(reply, request_id, None, &DecodeLimits::default())
+        })
+    }
+}
+
+/// Binds the socket of a session to the local address, or any IPv4 address if `None`.
+fn bind_session(bind_addr: Option<SocketAddr>) -> io::Result<UdpSocket> {
+    match bind_addr {
+        Some(bind_addr) => UdpSocket::bind(bind_addr),
+        None => UdpSocket::bind("0.0.0.0:0"),
+    }
+}
+
+/// Receives SNMPv1 traps sent to a UDP socket.
+///
+/// #Examples
+/// ```
+/// use std::net::Ipv4Addr;
+/// use rust_snmp::snmpv1::{send_trap, Trap, TrapListener};
+/// use rust_snmp::types::{GenericTrap, SnmpType};
+///
+/// let listener = TrapListener::bind("127.0.0.1:0", Some(b"public".to_vec())).unwrap();
+/// let address = listener.local_addr().unwrap().to_string();
+///
+/// let trap = Trap {
+///     enterprise: vec![1, 3, 6, 1, 4, 1, 2021].into(),
+///     agent_address: Ipv4Addr::new(192, 168, 0, 1),
+///     generic_trap: GenericTrap::LinkDown,
+///     specific_trap: 0,
+///     timestamp: 1500,
+///     varbinds: vec![(vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 1, 2].into(), SnmpType::SnmpInteger(2))],
+/// };
+/// send_trap(&address, "public", &trap).unwrap();
+///
+/// let (received, _) = listener.recv().unwrap();
+/// assert_eq!(received.generic_trap, GenericTrap::LinkDown);
+/// assert_eq!(received.enterprise, trap.enterprise);
+/// assert_eq!(received.varbinds[0].1.as_integer(), Some(2));
+///
+/// // Traps sent with any other community are rejected.
+/// send_trap(&address, "private", &trap).unwrap();
+/// assert!(listener.recv().is_err());
+/// ```
+#[derive(Debug)]
+pub struct TrapListener {
+    socket: UdpSocket,
+    /// The community traps must be sent with, or `None` to accept any community.
+    pub community: Option<Vec<u8>>,
+    /// Limits on what the parser accepts in a trap, so a hostile sender can not make it do
+    /// unbounded work. Traps past them fail with `SnmpError::LimitExceeded`.
+    pub decode_limits: DecodeLimits,
+}
+
+impl TrapListener {
+    /// Listens for traps on the standard trap port, `DEFAULT_TRAP_PORT`, which usually needs elevated privileges.
+    pub fn new(community: Option<Vec<u8>>) -> Result<TrapListener, SnmpError> {
+        TrapListener::bind(("0.0.0.0", DEFAULT_TRAP_PORT), community)
+    }
+
+    /// Listens for traps on the given local address.
+    pub fn bind<A: ToSocketAddrs>(address: A, community: Option<Vec<u8>>) -> Result<TrapListener, SnmpError> {
+        Ok(TrapListener {
+            socket: UdpSocket::bind(address)?,
+            community: community,
+            decode_limits: DecodeLimits::default(),
+        })
+    }
+
+    /// Sets the limits on what the parser accepts in a trap.
+    ///
+    /// #Examples
+    /// ```
+    /// use std::net::Ipv4Addr;
+    /// use rust_snmp::snmpv1::{send_trap, Trap, TrapListener};
+    /// use rust_snmp::types::{DecodeLimits, GenericTrap, SnmpError, SnmpType};
+    ///
+    /// let listener = TrapListener::bind("127.0.0.1:0", None).unwrap()
+    ///     .with_decode_limits(DecodeLimits { max_varbinds: 2, ..DecodeLimits::default() });
+    /// let address = listener.local_addr().unwrap().to_string();
+    ///
+    /// let trap = Trap {
+    ///     enterprise: vec![1, 3, 6, 1, 4, 1, 2021].into(),
+    ///     agent_address: Ipv4Addr::new(192, 168, 0, 1),
+    ///     generic_trap: GenericTrap::EnterpriseSpecific,
+    ///     specific_trap: 1,
+    ///     timestamp: 1500,
+    ///     varbinds: (0..3).map(|i| (vec![1, 3, 6, 1, 4, 1, 2021, i].into(), SnmpType::SnmpNull)).collect(),
+    /// };
+    /// send_trap(&address, "public", &trap).unwrap();
+    /// match listener.recv() {
+    ///     Err(SnmpError::LimitExceeded) => (),
+    ///     other => panic!("expected the limit to be exceeded, got {:?}", other),
+    /// }
+    /// ```
+    pub fn with_decode_limits(mut self, limits: DecodeLimits) -> TrapListener {
+        self.decode_limits = limits;
+        self
+    }
+
+    /// Returns the local address the listener is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr, SnmpError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Blocks until a trap arrives, returning it along with the address it was sent from.
+    pub fn recv(&self) -> Result<(Trap, SocketAddr), SnmpError> {
+        let mut receivepacket: [u8; 65535] = [0; 65535];
+        let (length, from) = self.socket.recv_from(&mut receivepacket)?;
+        let trap = check_trap(&receivepacket[..length], self.community.as_ref(), &self.decode_limits)?;
+        Ok((trap, from))
+    }
+
+    /// Turns the listener into one that receives traps without blocking.
+    /// Requires the `async` feature and must be called within a tokio runtime.
+    #[cfg(feature = "async")]
+    pub fn into_async(self) -> Result<AsyncTrapListener, SnmpError> {
+        self.socket.set_nonblocking(true)?;
+        Ok(AsyncTrapListener {
+            socket: tokio::net::UdpSocket::from_std(self.socket)?,
+            receivepacket: Mutex::new(vec![0; MAX_DATAGRAM_SIZE]),
+            community: self.community,
+            decode_limits: self.decode_limits,
+        })
+    }
+}
+
+/// Receives SNMPv1 traps without blocking, as a stream of traps.
+/// Requires the `async` feature.
+///
+/// #Examples
+/// ```no_run
+/// extern crate rust_snmp;
+/// extern crate tokio;
+/// use rust_snmp::snmpv1::TrapListener;
+/// # fn main() {
+/// let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// let _context = runtime.enter();
+/// let listener = TrapListener::new(None).unwrap().into_async().unwrap();
+/// loop {
+///     let (trap, from) = runtime.block_on(listener.recv()).unwrap();
+///     println!("{} sent {:?}", from, trap);
+/// }
+/// # }
+/// ```
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct AsyncTrapListener {
+    socket: tokio::net::UdpSocket,
+    /// Kept between polls, so a poll does not put a whole datagram on the stack.
+    receivepacket: Mutex<Vec<u8>>,
+    /// The community traps must be sent with, or `None` to accept any community.
+    pub community: Option<Vec<u8>>,
+    /// Limits on what the parser accepts in a trap. Traps past them fail with `SnmpError::LimitExceeded`.
+    pub decode_limits: DecodeLimits,
+}
+
+#[cfg(feature = "async")]
+impl AsyncTrapListener {
+    /// Polls for the next trap, the way a stream is polled. The stream never ends,
+    /// so this never resolves to `None`.
+    pub fn poll_next(&self, cx: &mut Context) -> Poll<Option<Result<(Trap, SocketAddr), SnmpError>>> {
+        let mut receivepacket = self.receivepacket.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut buf = tokio::io::ReadBuf::new(&mut receivepacket);
+        match self.socket.poll_recv_from(cx, &mut buf) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(SnmpError::Io(e)))),
+            Poll::Ready(Ok(from)) => {
+                let trap = check_trap(buf.filled(), self.community.as_ref(), &self.decode_limits);
+                Poll::Ready(Some(trap.map(|trap| (trap, from))))
+            }
+        }
+    }
+
+    /// Waits for the next trap, returning it along with the address it was sent from.
+    pub fn recv<'a>(&'a self) -> impl Future<Output = Result<(Trap, SocketAddr), SnmpError>> + 'a {
+        future::poll_fn(move |cx| match self.poll_next(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(trap) => Poll::Ready(trap.expect("the trap stream never ends")),
+        })
+    }
+}
+
+/// Parses a trap within the limits, checking its community if one is given.
+fn check_trap(packet: &[u8], community: Option<&Vec<u8>>, limits: &DecodeLimits) -> Result<Trap, SnmpError> {
+    let (trap, trap_community) = locate_truncation(packet, parse_trap(packet, limits), limits)?;
+    match community {
+        Some(community) if *community != trap_community => Err(SnmpError::CommunityMismatch),
+        _ => Ok(trap),
+    }
+}
+
+/// Parses a reply to the request, checking its community if one is given.
+fn parse_reply(packet: &[u8], request_id: u32, community: Option<&[u8]>,
+               limits: &DecodeLimits) -> Result<Message, SnmpError> {
+    let message = Message::from_packet(packet, request_id, limits)?;
+    match community {
+        Some(community) if message.community != community => Err(SnmpError::CommunityMismatch),
+        _ => Ok(message),
+    }
+}
+
+/// Sorts walk results by OID, dropping those found twice when the subtrees walked overlap.
+pub(crate) fn sort_varbinds(mut varbinds: Vec<(Oid, SnmpType)>) -> Vec<(Oid, SnmpType)> {
+    varbinds.sort_by(|a, b| a.0.cmp(&b.0));
+    varbinds.dedup_by(|a, b| a.0 == b.0);
+    varbinds
+}
+
+/// Fails with the error an agent reported in place of a response, and returns any other message.
+fn reject_report(message: Message) -> Result<Message, SnmpError> {
+    if message.pdu_type != PduType::Report {
+        return Ok(message);
+    }
+    match message.varbinds.into_iter().next() {
+        Some((oid, _)) => Err(SnmpError::ReportError(SnmpV3Error::from(oid))),
+        None => Err(SnmpError::ParsingError),
+    }
+}
+
+/// Checks that every OID can be encoded, before any of them are sent.
+pub(crate) fn validate_oids(oids: &[Oid]) -> Result<(), SnmpError> {
+    for oid in oids {
+        oid.validate()?;
+    }
+    Ok(())
+}
+
+/// Reads the header of a SNMPv1 or SNMPv2c message: its version and community.
+fn read_message_header(iterator: &mut slice::Iter<u8>) -> Result<(SnmpVersion, Vec<u8>), SnmpError> {
+    // Confirm that the first bit is the SNMP flag.
+    if next_byte(iterator)? != 0x30 {
+        return Err(SnmpError::ParsingError);
+    }
+
+    read_message_length(iterator)?;
+
+    // Confirm the protocol is SNMPv1 or SNMPv2c.
+    let version = match extract_value(iterator)? {
+        SnmpType::SnmpInteger(0) => SnmpVersion::V1,
+        SnmpType::SnmpInteger(1) => SnmpVersion::V2c,
+        _ => return Err(SnmpError::ParsingError),
+    };
+
+    // Get the SNMP community, which may be any bytes.
+    let community = match extract_octets(iterator) {
+        Ok(community) => community,
+        Err(SnmpError::InvalidType) => return Err(SnmpError::ParsingError),
+        Err(e) => return Err(e),
+    };
+    Ok((version, community))
+}
+
+/// Reads the length of a message, checking the packet is at least that long.
+/// Some agents pad their datagrams, so anything past the end of the message is skipped.
+fn read_message_length(iterator: &mut slice::Iter<u8>) -> Result<(), SnmpError> {
+    let length = read_contents_length(iterator)?;
+    *iterator = iterator.as_slice()[..length].iter();
+    Ok(())
+}
+
+/// Reads a PDU of any type but a SNMPv1 trap.
+pub(crate) fn read_pdu(iterator: &mut slice::Iter<u8>, limits: &DecodeLimits) -> Result<Pdu, SnmpError> {
+    let pdu_type = next_byte(iterator)?;
+    if pdu_type & 0xE0 != 0xA0 || pdu_type == SNMP_TRAP_CODE {
+        return Err(SnmpError::ParsingError);
+    }
+    read_contents_length(iterator)?;
+
+    // The request ID, error status and error index are all integers.
+    let mut fields = [0; 3];
+    for field in &mut fields {
+        *field = match extract_value(iterator)? {
+            SnmpType::SnmpInteger(i) => i,
+            _ => return Err(SnmpError::ParsingError),
+        };
+    }
+
+    Ok(Pdu {
+        pdu_type: pdu_type.into(),
+        // The ID may come back negative, so keep its 32-bit pattern.
+        request_id: fields[0] as u32,
+        error_status: fields[1],
+        error_index: fields[2],
+        varbinds: parse_varbinds(iterator, limits)?,
+    })
+}
+
+/// Parses a sequence of variable bindings, each a sequence of an OID and its value.
+pub(crate) fn parse_varbinds(iterator: &mut slice::Iter<u8>,
+                             limits: &DecodeLimits) -> Result<Vec<(Oid, SnmpType)>, SnmpError> {
+    // Confirm next byte indicates a sequence of OID's and their values.
+    if next_byte(iterator)? != 0x30 {
+        return Err(SnmpError::ParsingError);
+    }
+
+    // Then the length of all the variable bindings together.
+    let length = read_contents_length(iterator)?;
+    let end = iterator.len() - length;
+
+    let mut varbinds = Vec::new();
+    while iterator.len() > end {
+        if varbinds.len() == limits.max_varbinds {
+            return Err(SnmpError::LimitExceeded);
+        }
+
+        // Each variable binding is a sequence...
+        if next_byte(iterator)? != 0x30 {
+            return Err(SnmpError::ParsingError);
+        }
+
+        // With an associated length...
+        let length = read_contents_length(iterator)?;
+        let varbind_end = iterator.len() - length;
+        
+        // Holding the OID...
+        let oid = match extract_value(iterator)? {
+            SnmpType::SnmpObjectID(o) => o,
+            _ => return Err(SnmpError::ParsingError),
+        };
+
+        // And finally, the actual data.
+        let datatype = extract_value_within(iterator, limits.max_depth)?;
+
+        // Both of which must fit in the sequence they were declared in.
+        if iterator.len() != varbind_end || iterator.len() < end {
+            return Err(SnmpError::ParsingError);
+        }
+        trace!("parsed {} = {}", oid, datatype);
+        varbinds.push((oid, datatype));
+    }
+    Ok(varbinds)
+}
+
+/// Adds the port to the address unless it already ends in one,
+/// putting brackets around IPv6 addresses so the port can be told apart.
+pub(crate) fn with_port<'a>(address: &'a str, port: u16) -> Cow<'a, str> {
+    let has_port = address.rfind(':').is_some_and(|colon| {
+        // IPv6 addresses are full of colons, so they need brackets around them to have a port.
+        address[colon + 1..].parse::<u16>().is_ok() && (address.starts_with('[') || !address[..colon].contains(':'))
+    });
+    if has_port {
+        Cow::Borrowed(address)
+    } else if address.contains(':') && !address.starts_with('[') {
+        Cow::Owned(format!("[{}]:{}", address, port))
+    } else {
+        Cow::Owned(format!("{}:{}", address, port))
+    }
+}
+
+/// Resolves the address of an agent, picking the first address it resolves to.
+pub(crate) fn resolve(address: &str) -> io::Result<SocketAddr> {
+    address.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "the address did not resolve")
+    })
+}
+
+/// Binds a UDP socket to the local address, or to any address of the same family
+/// as the target if `None`.
+pub(crate) fn bind(bind_addr: Option<SocketAddr>, target: &SocketAddr) -> io::Result<UdpSocket> {
+    match bind_addr {
+        Some(bind_addr) => UdpSocket::bind(bind_addr),
+        None if target.is_ipv6() => UdpSocket::bind("[::]:0"),
+        None => UdpSocket::bind("0.0.0.0:0"),
+    }
+}
+
+/// Binds a UDP socket like `bind` and connects it to the target, so only replies from the
+/// target are received and ICMP errors about it are reported.
+pub(crate) fn connect(bind_addr: Option<SocketAddr>, target: &SocketAddr) -> io::Result<UdpSocket> {
+    let socket = bind(bind_addr, target)?;
+    socket.connect(target)?;
+    Ok(socket)
+}
+
+/// Sorts the IO errors that mean the agent could not be reached, or did not answer in time,
+/// out from other IO errors.
+pub(crate) fn classify(error: io::Error) -> SnmpError {
+    match error.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => SnmpError::Timeout,
+        io::ErrorKind::ConnectionRefused | io::ErrorKind::HostUnreachable |
+        io::ErrorKind::NetworkUnreachable => SnmpError::Unreachable,
+        _ => SnmpError::Io(error),
+    }
+}
+
+/// How long to wait for a reply, how many times to resend the request, and how long to wait before each send.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RetryPolicy {
+    /// How long to wait for a reply, in milliseconds. This is doubled for every retry.
+    pub(crate) timeout: u64,
+    /// How many times to resend a request if no reply arrives.
+    pub(crate) retries: u32,
+    /// The longest random delay before each send, if any.
+    pub(crate) jitter: Option<time::Duration>,
+}
+
+/// Sends the packet and waits for a reply, resending with a doubled timeout on each retry,
+/// and waiting up to `jitter` before each send. Each reply is written to `receivepacket` and
+/// handed to `parse`. Replies that fail with `SnmpError::RequestIdMismatch` answer some other
+/// request, so they are thrown away and the wait goes on until the timeout.
+/// A reply that fills the whole buffer may have been cut off, so the buffer should be one byte
+/// larger than the largest reply to accept.
+pub(crate) fn exchange<T, F>(socket: &UdpSocket, address: SocketAddr, sendpacket: &[u8], policy: RetryPolicy,
+                             receivepacket: &mut [u8], mut parse: F)
+                             -> Result<T, SnmpError> where F: FnMut(&[u8]) -> Result<T, SnmpError> {
+    let RetryPolicy { mut timeout, retries, jitter } = policy;
+    let mut attempt = 0;
+    loop {
+        if let Some(jitter) = jitter {
+            thread::sleep(random_delay(jitter));
+        }
+        debug!("sending {} bytes to {}: {}", sendpacket.len(), address, Hex(sendpacket));
+        send_datagram(socket, address, sendpacket).map_err(classify)?;
+        let deadline = time::Instant::now() + time::Duration::from_millis(timeout);
+        let received = loop {
+            let remaining = deadline.saturating_duration_since(time::Instant::now());
+            if remaining == time::Duration::from_secs(0) {
+                break Err(SnmpError::Timeout);
+            }
+            socket.set_read_timeout(Some(remaining))?;
+            match socket.recv_from(receivepacket).map_err(classify) {
+                Ok((length, _)) if length >= receivepacket.len() => return Err(SnmpError::ResponseTooLarge),
+                Ok((length, from)) => {
+                    debug!("received {} bytes from {}: {}", length, from, Hex(&receivepacket[..length]));
+                    match parse(&receivepacket[..length]) {
+                        Err(SnmpError::RequestIdMismatch) => debug!("discarding a reply to another request"),
+                        result => break result,
+                    }
+                },
+                Err(e) => break Err(e),
+            }
+        };
+        match received {
+            Err(SnmpError::Timeout) => {
+                debug!("no reply from {} within {}ms", address, timeout);
+                if attempt == retries { return Err(SnmpError::Timeout); }
+                attempt += 1;
+                timeout = timeout.saturating_mul(2);
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Allocates a buffer for replies of up to `max_message_size` bytes, with one byte more
+/// to tell a reply that was cut off from one that fits.
+pub(crate) fn receive_buffer(max_message_size: usize) -> Vec<u8> {
+    vec![0; cmp::min(max_message_size, MAX_DATAGRAM_SIZE).saturating_add(1)]
+}
+
+/// Sends the packet to the address, with `send` if the socket is connected to it,
+/// since macOS and the BSDs refuse `send_to` on a connected socket.
+fn send_datagram(socket: &UdpSocket, address: SocketAddr, packet: &[u8]) -> io::Result<usize> {
+    match socket.peer_addr() {
+        Ok(peer) if peer == address => socket.send(packet),
+        _ => socket.send_to(packet, address),
+    }
+}
+
+/// Sends the packet over a new TCP connection and reads one message back. A BER message
+/// carries its own length, so it needs no other framing on the stream.
+fn exchange_tcp(address: SocketAddr, sendpacket: &[u8], timeout: u64,
+                max_message_size: usize) -> Result<Vec<u8>, SnmpError> {
+    let timeout = time::Duration::from_millis(timeout);
+    let mut stream = TcpStream::connect_timeout(&address, timeout).map_err(classify)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    debug!("sending {} bytes to {} over TCP: {}", sendpacket.len(), address, Hex(sendpacket));
+    stream.write_all(sendpacket).map_err(classify)?;
+
+    // Read the tag and the first length byte, then any further length bytes, then the contents.
+    let mut receivepacket = vec![0; 2];
+    stream.read_exact(&mut receivepacket).map_err(classify)?;
+    if receivepacket[1] & 0x80 != 0 {
+        let mut length = vec![0; (receivepacket[1] & 0x7F) as usize];
+        stream.read_exact(&mut length).map_err(classify)?;
+        receivepacket.extend_from_slice(&length);
+    }
+    let length = read_length(&mut receivepacket[1..].iter())?;
+    let header = receivepacket.len();
+    if header + length > max_message_size {
+        return Err(SnmpError::ResponseTooLarge);
+    }
+    receivepacket.resize(header + length, 0);
+    stream.read_exact(&mut receivepacket[header..]).map_err(classify)?;
+    debug!("received {} bytes from {} over TCP: {}", receivepacket.len(), address, Hex(&receivepacket));
+    Ok(receivepacket)
+}
+
+/// Picks a random delay no longer than `max`.
+pub(crate) fn random_delay(max: time::Duration) -> time::Duration {
+    max.mul_f64(rand::random::<f64>())
+}
+
+/// Appends a variable binding, which is a sequence of the OID and the value.
+pub(crate) fn encode_varbind(oid: &Oid, value: &SnmpType, buf: &mut Vec<u8>) {
+    let mut varbind = oid.encode_snmp();
+    varbind.append(&mut value.encode_snmp());
+    buf.push(0x30);                         // Start of sequence
+    write_length(varbind.len(), buf);       // Size
+    buf.append(&mut varbind);
+}
+
+/// Wraps the PDU in a message with the version and community.
+fn encode_message(version: SnmpVersion, community: &[u8], mut pdu: Vec<u8>) -> Vec<u8> {
+    // SNMP version
+    let mut message = Vec::with_capacity(16 + community.len() + pdu.len());
+    message.append(&mut (version as u8).encode_snmp());
+
+    // Community
+    message.append(&mut community.encode_snmp());
+
+    // PDU
+    message.append(&mut pdu);
+
+    // SNMP sequence
+    let mut buf = Vec::with_capacity(4 + message.len());
+    buf.push(0x30);
+    write_length(message.len(), &mut buf);
+    buf.append(&mut message);
+    buf
+}
+
+/// Writes the PDU `Pdu::encode` would, asking for the OIDs each bound to the value,
+/// into a fixed buffer, back to front.
+pub(crate) fn write_pdu(pdu_type: u8, request_id: u32, fields: (u32, u32),
+                        oids: &[Oid], value: &SnmpType, writer: &mut BufferWriter) -> Result<(), SnmpError> {
+    let end = writer.len();
+
+    // Variable bindings, last one first
+    for oid in oids.iter().rev() {
+        let varbind_end = writer.len();
+        value.write_snmp(writer)?;
+        oid.write_snmp(writer)?;
+        writer.prepend_header(0x30, varbind_end)?;
+    }
+    writer.prepend_header(0x30, end)?;
+
+    (fields.1 as i64).write_snmp(writer)?;  // Error index or max-repetitions
+    (fields.0 as i64).write_snmp(writer)?;  // Error status or non-repeaters
+    request_id.write_snmp(writer)?;         // Request ID
+    writer.prepend_header(pdu_type, end)
+}
+
+/// Encodes a PDU around variable bindings that are already encoded.
+fn encode_pdu_varbinds(pdu_type: u8, request_id: u32, fields: (i64, i64), mut varbinds: Vec<u8>) -> Vec<u8> {
+    // PDU contents
+    let mut pdu = Vec::with_capacity(16 + varbinds.len());
+    pdu.append(&mut request_id.encode_snmp());        // Request ID
+    pdu.append(&mut fields.0.encode_snmp());          // Error status or non-repeaters
+    pdu.append(&mut fields.1.encode_snmp());          // Error index or max-repetitions
+    pdu.push(0x30);                                    // Start of sequence
+    write_length(varbinds.len(), &mut pdu);            // Size
+    pdu.append(&mut varbinds);
+
+    let mut buf = Vec::with_capacity(4 + pdu.len());
+    buf.push(pdu_type);                 // GET, GETNEXT, SET, GETBULK or INFORM request
+    write_length(pdu.len(), &mut buf);  // PDU size
+    buf.append(&mut pdu);
+    buf
 }
@@ -1,128 +1,1072 @@
 //! Contains functions and structs for sending and receiving SNMPv3 messages.
 
-use types;
-use std::net::UdpSocket;
-use std::time;
-/// Sends a SMTPv3 message and returns the reply or an error specifiying what went wrong.
+use std::net::{SocketAddr, UdpSocket};
+use std::ops::Range;
+use std::{fmt, slice};
+use std::sync::Arc;
+use std::time::Instant;
+use types::*;
+use traits::*;
+use snmpv1::{self, Message, Pdu};
+use rand;
+use md5::{Md5, Digest};
+use sha1::Sha1;
+use sha2::{Sha224, Sha256, Sha384, Sha512};
+use hmac::{Hmac, Mac};
+use des::Des;
+use cbc::{Decryptor, Encryptor};
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use cbc::cipher::block_padding::NoPadding;
+use aes::Aes128;
+use cfb_mode::cipher::AsyncStreamCipher;
+use byteorder::{BigEndian, ByteOrder};
+
+const SNMP_VERSION_3: u8          = 0x03;
+const SNMP_USM_SECURITY_MODEL: u8 = 0x03;
+const SNMP_MAX_MESSAGE_SIZE: u32  = 65507;
+
+// Message flags
+const SNMP_FLAGS_REPORTABLE: u8     = 0b0000_0100;
+const SNMP_FLAGS_PRIVATE: u8        = 0b0000_0010;
+const SNMP_FLAGS_AUTHENTICATED: u8  = 0b0000_0001;
+
+// Amount of repeated password hashed when deriving a key
+const SNMP_PASSWORD_EXPANSION: usize = 1_048_576;
+
+// Block size of DES, which scoped PDUs are padded to
+const SNMP_DES_BLOCK_LENGTH: usize = 8;
+
+/// A protocol for authenticating messages, which the User-based Security Model uses to localize
+/// keys and sign messages. Implement this to use protocols other than the built-in ones,
+/// with `Authentication::Custom`.
 ///
 /// #Examples
 /// ```
-/// rust_snmp::snmpv3::smtpv3_send("demo.snmplabs.com:161",
-///                                           "public",
-///                                           &[1, 3, 6, 1, 2, 1, 1, 5, 0]);
+/// extern crate hmac;
+/// extern crate rust_snmp;
+/// extern crate sha1;
+/// use std::sync::Arc;
+/// use hmac::{Hmac, Mac};
+/// use sha1::Sha1;
+/// use rust_snmp::snmpv3::{AuthProtocol, Authentication, HmacSha1};
+///
+/// // A protocol that signs with HMAC-SHA but keeps the whole 20-byte digest.
+/// #[derive(Debug)]
+/// struct FullSha1;
+///
+/// impl AuthProtocol for FullSha1 {
+///     fn localize_key(&self, password: &[u8], engine_id: &[u8]) -> Vec<u8> {
+///         HmacSha1.localize_key(password, engine_id)
+///     }
+///     fn digest(&self, key: &[u8], message: &[u8]) -> Vec<u8> {
+///         let mut mac = Hmac::<Sha1>::new_from_slice(key).unwrap();
+///         mac.update(message);
+///         mac.finalize().into_bytes().to_vec()
+///     }
+///     fn digest_length(&self) -> usize {
+///         20
+///     }
+/// }
+///
+/// # fn main() {
+/// let engine_id = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2];
+/// let custom = Authentication::Custom(Arc::new(FullSha1), "maplesyrup".to_owned());
+/// assert_eq!(custom.localize_key(&engine_id), Authentication::Sha1("maplesyrup".to_owned()).localize_key(&engine_id));
+/// assert_eq!(custom.protocol().digest_length(), 20);
+///
+/// // Test case 1 of RFC 2202. HMAC-SHA-96 sends the first 12 bytes of the same digest.
+/// let digest = FullSha1.digest(&[0x0b; 20], b"Hi There");
+/// assert_eq!(digest, vec![0xb6, 0x17, 0x31, 0x86, 0x55, 0x05, 0x72, 0x64, 0xe2, 0x8b,
+///                         0xc0, 0xb6, 0xfb, 0x37, 0x8c, 0x8e, 0xf1, 0x46, 0xbe, 0x00]);
+/// assert_eq!(HmacSha1.digest(&[0x0b; 20], b"Hi There"), &digest[..12]);
+/// # }
 /// ```
-pub fn smtpv3_send(addr: &str,
-                    community: &str,
-                    mibvals: &[u16]) {
-    let mut buf: [u8; 1024] = [0; 1024];
-    /*let mut mib: [u8; 1024] = [0; 1024];
-    let orgmiblen = mibvals.len();
-    let mut miblen = orgmiblen;
-
-    let mut counter = 0;
-    for mibval in mibvals.iter() {
-        if mibval > &127u16 {
-            mib[counter] = (128 + (mibval / 128)) as u8;
-            mib[counter + 1] = (mibval - (mibval - ((mibval / 128) * 128))) as u8;
-            counter += 2;
-            miblen += 1;
+pub trait AuthProtocol: fmt::Debug + Send + Sync {
+    /// Derives the key localized to the engine from a password.
+    fn localize_key(&self, password: &[u8], engine_id: &[u8]) -> Vec<u8>;
+
+    /// Computes the digest of a whole message with a localized key, as sent in the
+    /// authentication parameters. It must be `digest_length` bytes long.
+    fn digest(&self, key: &[u8], message: &[u8]) -> Vec<u8>;
+
+    /// Returns the length of the digests, which is reserved in a message before it is signed.
+    fn digest_length(&self) -> usize;
+}
+
+/// A protocol for encrypting scoped PDUs. Implement this to use protocols other than the built-in
+/// ones, with `Privacy::Custom`.
+///
+/// `encrypt` and `decrypt` are given the key from `localize_key`, and may assume it is as long as
+/// that gives. `CbcDes` takes its key from the first 8 bytes and its pre-IV from the next 8, and
+/// `CfbAes128` takes its key from the first 16 bytes, so both panic on keys shorter than 16 bytes.
+/// Every built-in authentication protocol localizes keys at least that long, but a custom one
+/// used with them must too.
+pub trait PrivProtocol: fmt::Debug + Send + Sync {
+    /// Derives the key localized to the engine from a password, with the hash function
+    /// of the authentication protocol. Protocols needing longer keys than the hash gives may extend it.
+    fn localize_key(&self, auth: &dyn AuthProtocol, password: &[u8], engine_id: &[u8]) -> Vec<u8> {
+        auth.localize_key(password, engine_id)
+    }
+
+    /// Encrypts a scoped PDU with the localized key, returning the ciphertext and the salt
+    /// to send as privacy parameters.
+    fn encrypt(&self, key: &[u8], engine: &Engine, data: Vec<u8>) -> (Vec<u8>, Vec<u8>);
+
+    /// Decrypts a scoped PDU with the localized key and the salt the agent sent as privacy parameters.
+    fn decrypt(&self, key: &[u8], engine: &Engine, salt: &[u8], data: Vec<u8>) -> Result<Vec<u8>, SnmpError>;
+}
+
+/// Defines an authentication protocol that signs with HMAC over a hash function,
+/// keeping the first bytes of the digest.
+macro_rules! hmac_protocol {
+    ($(#[$doc:meta])* $name:ident, $hash:ty, $digest_length:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy)]
+        pub struct $name;
+
+        impl AuthProtocol for $name {
+            fn localize_key(&self, password: &[u8], engine_id: &[u8]) -> Vec<u8> {
+                localize_key::<$hash>(password, engine_id)
+            }
+
+            fn digest(&self, key: &[u8], message: &[u8]) -> Vec<u8> {
+                let mut mac = Hmac::<$hash>::new_from_slice(key).expect("HMAC accepts keys of any length");
+                mac.update(message);
+                mac.finalize().into_bytes()[..$digest_length].to_vec()
+            }
+
+            fn digest_length(&self) -> usize {
+                $digest_length
+            }
+        }
+    };
+}
+
+hmac_protocol!(
+    /// HMAC-MD5-96, as described in RFC 3414.
+    HmacMd5, Md5, 12);
+hmac_protocol!(
+    /// HMAC-SHA-96, as described in RFC 3414.
+    HmacSha1, Sha1, 12);
+hmac_protocol!(
+    /// HMAC-SHA-224 truncated to 128 bits, as described in RFC 7860.
+    HmacSha224, Sha224, 16);
+hmac_protocol!(
+    /// HMAC-SHA-256 truncated to 192 bits, as described in RFC 7860.
+    HmacSha256, Sha256, 24);
+hmac_protocol!(
+    /// HMAC-SHA-384 truncated to 256 bits, as described in RFC 7860.
+    HmacSha384, Sha384, 32);
+hmac_protocol!(
+    /// HMAC-SHA-512 truncated to 384 bits, as described in RFC 7860.
+    HmacSha512, Sha512, 48);
+
+/// CBC-DES, as described in RFC 3414. Needs a localized key of at least 16 bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct CbcDes;
+
+impl PrivProtocol for CbcDes {
+    fn encrypt(&self, key: &[u8], engine: &Engine, mut data: Vec<u8>) -> (Vec<u8>, Vec<u8>) {
+        // The salt is the engine boots followed by a local integer.
+        let mut salt = vec![0; 8];
+        BigEndian::write_u32(&mut salt[0..4], engine.boots);
+        BigEndian::write_u32(&mut salt[4..8], rand::random::<u32>());
+
+        let length = data.len().div_ceil(SNMP_DES_BLOCK_LENGTH) * SNMP_DES_BLOCK_LENGTH;
+        data.resize(length, 0);
+        Encryptor::<Des>::new_from_slices(&key[0..8], &des_iv(key, &salt))
+            .expect("DES keys and IVs are 8 bytes")
+            .encrypt_padded_mut::<NoPadding>(&mut data, length)
+            .expect("data is padded to the block size");
+        (data, salt)
+    }
+
+    fn decrypt(&self, key: &[u8], _engine: &Engine, salt: &[u8], mut data: Vec<u8>) -> Result<Vec<u8>, SnmpError> {
+        if salt.len() != 8 || data.len() % SNMP_DES_BLOCK_LENGTH != 0 {
+            return Err(SnmpError::ParsingError);
+        }
+        Decryptor::<Des>::new_from_slices(&key[0..8], &des_iv(key, salt))
+            .expect("DES keys and IVs are 8 bytes")
+            .decrypt_padded_mut::<NoPadding>(&mut data)
+            .map_err(|_| SnmpError::ParsingError)?;
+        Ok(data)
+    }
+}
+
+/// AES-128 in CFB mode, as described in RFC 3826. Needs a localized key of at least 16 bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct CfbAes128;
+
+impl PrivProtocol for CfbAes128 {
+    fn encrypt(&self, key: &[u8], engine: &Engine, mut data: Vec<u8>) -> (Vec<u8>, Vec<u8>) {
+        // The salt is a local 64-bit integer.
+        let mut salt = vec![0; 8];
+        BigEndian::write_u64(&mut salt, rand::random::<u64>());
+
+        cfb_mode::Encryptor::<Aes128>::new_from_slices(&key[0..16], &aes_iv(engine, &salt))
+            .expect("AES-128 keys and IVs are 16 bytes")
+            .encrypt(&mut data);
+        (data, salt)
+    }
+
+    fn decrypt(&self, key: &[u8], engine: &Engine, salt: &[u8], mut data: Vec<u8>) -> Result<Vec<u8>, SnmpError> {
+        if salt.len() != 8 {
+            return Err(SnmpError::ParsingError);
+        }
+        cfb_mode::Decryptor::<Aes128>::new_from_slices(&key[0..16], &aes_iv(engine, salt))
+            .expect("AES-128 keys and IVs are 16 bytes")
+            .decrypt(&mut data);
+        Ok(data)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The protocol and password used to authenticate a request.
+pub enum Authentication {
+    /// HMAC-MD5-96, keyed with the given password.
+    Md5(String),
+    /// HMAC-SHA-96, keyed with the given password.
+    Sha1(String),
+    /// HMAC-SHA-224 with a 128-bit digest, keyed with the given password.
+    Sha224(String),
+    /// HMAC-SHA-256 with a 192-bit digest, keyed with the given password.
+    Sha256(String),
+    /// HMAC-SHA-384 with a 256-bit digest, keyed with the given password.
+    Sha384(String),
+    /// HMAC-SHA-512 with a 384-bit digest, keyed with the given password.
+    Sha512(String),
+    /// Any other protocol, keyed with the given password.
+    Custom(Arc<dyn AuthProtocol>, String),
+}
+
+impl Authentication {
+    /// Derives the key localized to the given engine from the password.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv3::Authentication;
+    /// // Test vectors from RFC 3414, appendix A.3.
+    /// let engine_id = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2];
+    /// assert_eq!(Authentication::Md5("maplesyrup".to_owned()).localize_key(&engine_id),
+    ///            vec![0x52, 0x6f, 0x5e, 0xed, 0x9f, 0xcc, 0xe2, 0x6f,
+    ///                 0x89, 0x64, 0xc2, 0x93, 0x07, 0x87, 0xd8, 0x2b]);
+    /// assert_eq!(Authentication::Sha1("maplesyrup".to_owned()).localize_key(&engine_id),
+    ///            vec![0x66, 0x95, 0xfe, 0xbc, 0x92, 0x88, 0xe3, 0x62, 0x82, 0x23,
+    ///                 0x5f, 0xc7, 0x15, 0x1f, 0x12, 0x84, 0x97, 0xb3, 0x8f, 0x3f]);
+    ///
+    /// // The SHA-2 protocols of RFC 7860 localize keys the same way, with their own hash function.
+    /// assert_eq!(Authentication::Sha256("maplesyrup".to_owned()).localize_key(&engine_id),
+    ///            vec![0x89, 0x82, 0xe0, 0xe5, 0x49, 0xe8, 0x66, 0xdb, 0x36, 0x1a, 0x6b, 0x62, 0x5d, 0x84, 0xcc, 0xcc,
+    ///                 0x11, 0x16, 0x2d, 0x45, 0x3e, 0xe8, 0xce, 0x3a, 0x64, 0x45, 0xc2, 0xd6, 0x77, 0x6f, 0x0f, 0x8b]);
+    /// assert_eq!(Authentication::Sha224("maplesyrup".to_owned()).localize_key(&engine_id),
+    ///            vec![0x0b, 0xd8, 0x82, 0x7c, 0x6e, 0x29, 0xf8, 0x06, 0x5e, 0x08, 0xe0, 0x92, 0x37, 0xf1,
+    ///                 0x77, 0xe4, 0x10, 0xf6, 0x9b, 0x90, 0xe1, 0x78, 0x2b, 0xe6, 0x82, 0x07, 0x56, 0x74]);
+    /// assert_eq!(Authentication::Sha384("maplesyrup".to_owned()).localize_key(&engine_id)[..8],
+    ///            [0x3b, 0x29, 0x8f, 0x16, 0x16, 0x4a, 0x11, 0x18]);
+    /// assert_eq!(Authentication::Sha512("maplesyrup".to_owned()).localize_key(&engine_id)[..8],
+    ///            [0x22, 0xa5, 0xa3, 0x6c, 0xed, 0xfc, 0xc0, 0x85]);
+    ///
+    /// // Their digests are truncated to different lengths.
+    /// let sha2 = [Authentication::Sha224(String::new()), Authentication::Sha256(String::new()),
+    ///             Authentication::Sha384(String::new()), Authentication::Sha512(String::new())];
+    /// let lengths: Vec<usize> = sha2.iter().map(|auth| auth.protocol().digest_length()).collect();
+    /// assert_eq!(lengths, vec![16, 24, 32, 48]);
+    /// ```
+    pub fn localize_key(&self, engine_id: &[u8]) -> Vec<u8> {
+        self.protocol().localize_key(self.password().as_bytes(), engine_id)
+    }
+
+    /// Returns the protocol that authenticates the request.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv3::Authentication;
+    /// // Test case 2 of RFC 4231, truncated to the digest lengths of RFC 7860.
+    /// let (key, message) = (b"Jefe", b"what do ya want for nothing?");
+    /// assert_eq!(Authentication::Sha224(String::new()).protocol().digest(key, message),
+    ///            vec![0xa3, 0x0e, 0x01, 0x09, 0x8b, 0xc6, 0xdb, 0xbf, 0x45, 0x69, 0x0f, 0x3a, 0x7e, 0x9e, 0x6d, 0x0f]);
+    /// assert_eq!(Authentication::Sha256(String::new()).protocol().digest(key, message),
+    ///            vec![0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26,
+    ///                 0x08, 0x95, 0x75, 0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83]);
+    /// assert_eq!(Authentication::Sha384(String::new()).protocol().digest(key, message),
+    ///            vec![0xaf, 0x45, 0xd2, 0xe3, 0x76, 0x48, 0x40, 0x31, 0x61, 0x7f, 0x78, 0xd2, 0xb5, 0x8a, 0x6b, 0x1b,
+    ///                 0x9c, 0x7e, 0xf4, 0x64, 0xf5, 0xa0, 0x1b, 0x47, 0xe4, 0x2e, 0xc3, 0x73, 0x63, 0x22, 0x44, 0x5e]);
+    /// assert_eq!(Authentication::Sha512(String::new()).protocol().digest(key, message),
+    ///            vec![0x16, 0x4b, 0x7a, 0x7b, 0xfc, 0xf8, 0x19, 0xe2, 0xe3, 0x95, 0xfb, 0xe7, 0x3b, 0x56, 0xe0, 0xa3,
+    ///                 0x87, 0xbd, 0x64, 0x22, 0x2e, 0x83, 0x1f, 0xd6, 0x10, 0x27, 0x0c, 0xd7, 0xea, 0x25, 0x05, 0x54,
+    ///                 0x97, 0x58, 0xbf, 0x75, 0xc0, 0x5a, 0x99, 0x4a, 0x6d, 0x03, 0x4f, 0x65, 0xf8, 0xf0, 0xe6, 0xfd]);
+    /// ```
+    pub fn protocol(&self) -> &dyn AuthProtocol {
+        match *self {
+            Authentication::Md5(_) => &HmacMd5,
+            Authentication::Sha1(_) => &HmacSha1,
+            Authentication::Sha224(_) => &HmacSha224,
+            Authentication::Sha256(_) => &HmacSha256,
+            Authentication::Sha384(_) => &HmacSha384,
+            Authentication::Sha512(_) => &HmacSha512,
+            Authentication::Custom(ref protocol, _) => &**protocol,
+        }
+    }
+
+    fn password(&self) -> &str {
+        match *self {
+            Authentication::Md5(ref password) |
+            Authentication::Sha1(ref password) |
+            Authentication::Sha224(ref password) |
+            Authentication::Sha256(ref password) |
+            Authentication::Sha384(ref password) |
+            Authentication::Sha512(ref password) |
+            Authentication::Custom(_, ref password) => password,
+        }
+    }
+}
+
+/// Localizes a password to an engine as described in RFC 3414, appendix A.2.
+fn localize_key<D: Digest>(password: &[u8], engine_id: &[u8]) -> Vec<u8> {
+    let mut hasher = D::new();
+    let mut block = [0u8; 64];
+    let mut password = password.iter().cycle();
+    for _ in 0..(SNMP_PASSWORD_EXPANSION / block.len()) {
+        for byte in block.iter_mut() {
+            *byte = *password.next().unwrap_or(&0);
+        }
+        hasher.update(&block[..]);
+    }
+    let key = hasher.finalize();
+
+    let mut hasher = D::new();
+    hasher.update(&key);
+    hasher.update(engine_id);
+    hasher.update(&key);
+    hasher.finalize().to_vec()
+}
+
+#[derive(Debug, Clone)]
+/// The protocol and password used to encrypt a request.
+/// The privacy key is localized with the hash function of the authentication protocol.
+pub enum Privacy {
+    /// CBC-DES, keyed with the given password.
+    Des(String),
+    /// AES-128 in CFB mode, keyed with the given password.
+    Aes128(String),
+    /// Any other protocol, keyed with the given password.
+    Custom(Arc<dyn PrivProtocol>, String),
+}
+
+impl Privacy {
+    /// Returns the protocol that encrypts the request.
+    pub fn protocol(&self) -> &dyn PrivProtocol {
+        match *self {
+            Privacy::Des(_) => &CbcDes,
+            Privacy::Aes128(_) => &CfbAes128,
+            Privacy::Custom(ref protocol, _) => &**protocol,
+        }
+    }
+
+    fn password(&self) -> &str {
+        match *self {
+            Privacy::Des(ref password) |
+            Privacy::Aes128(ref password) |
+            Privacy::Custom(_, ref password) => password,
+        }
+    }
+}
+
+/// Builds the DES IV by XORing the pre-IV at the end of the privacy key with the salt.
+fn des_iv(key: &[u8], salt: &[u8]) -> Vec<u8> {
+    key[8..16].iter().zip(salt).map(|(k, s)| k ^ s).collect()
+}
+
+/// Builds the AES IV from the engine boots, engine time and the salt, as described in RFC 3826.
+fn aes_iv(engine: &Engine, salt: &[u8]) -> Vec<u8> {
+    let mut iv = vec![0; 8];
+    BigEndian::write_u32(&mut iv[0..4], engine.boots);
+    BigEndian::write_u32(&mut iv[4..8], engine.time);
+    iv.extend_from_slice(salt);
+    iv
+}
+
+/// The PDU of a SNMPv3 message together with the context it applies to, which is what gets
+/// encrypted when privacy is used.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::snmpv1::Pdu;
+/// use rust_snmp::snmpv3::ScopedPdu;
+/// use rust_snmp::types::{PduType, SnmpType};
+///
+/// let pdu = Pdu::new(PduType::GetRequest, 7, vec![(vec![1, 3, 6, 1, 2, 1, 1, 5, 0].into(), SnmpType::SnmpNull)]);
+/// let scoped = ScopedPdu { context_engine_id: vec![0x80, 0x00, 0x1F, 0x88], context_name: Vec::new(), pdu: pdu };
+/// let bytes = scoped.encode();
+/// assert_eq!(&bytes[..8], &[0x30, 0x23, 0x04, 0x04, 0x80, 0x00, 0x1F, 0x88]);
+///
+/// let decoded = ScopedPdu::decode(&bytes).unwrap();
+/// assert_eq!(decoded.context_engine_id, [0x80, 0x00, 0x1F, 0x88]);
+/// assert_eq!(decoded.pdu.request_id, 7);
+/// assert_eq!(decoded.encode(), bytes);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScopedPdu {
+    /// The ID of the engine the context belongs to, which is the agent's own unless it is a proxy.
+    pub context_engine_id: Vec<u8>,
+    /// The name of the context, which is empty for the default context.
+    pub context_name: Vec<u8>,
+    /// The PDU.
+    pub pdu: Pdu,
+}
+
+impl ScopedPdu {
+    /// Encodes the scoped PDU, before any encryption.
+    /// The OIDs are not validated, as with `Pdu::encode`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut contents = Vec::with_capacity(40);
+        contents.append(&mut self.context_engine_id.encode_snmp());
+        contents.append(&mut self.context_name.encode_snmp());
+        contents.append(&mut self.pdu.encode());
+        let mut buf = vec![0x30];
+        write_length(contents.len(), &mut buf);
+        buf.append(&mut contents);
+        buf
+    }
+
+    /// Decodes a scoped PDU, after any decryption.
+    pub fn decode(bytes: &[u8]) -> Result<ScopedPdu, SnmpError> {
+        ScopedPdu::decode_with_limits(bytes, &DecodeLimits::default())
+    }
+
+    /// Decodes a scoped PDU like `decode`, within the given limits.
+    pub fn decode_with_limits(bytes: &[u8], limits: &DecodeLimits) -> Result<ScopedPdu, SnmpError> {
+        locate_truncation(bytes, read_scoped_pdu(&mut bytes.iter(), limits), limits)
+    }
+}
+
+fn read_scoped_pdu(iterator: &mut slice::Iter<u8>, limits: &DecodeLimits) -> Result<ScopedPdu, SnmpError> {
+    if next_byte(iterator)? != 0x30 {
+        return Err(SnmpError::ParsingError);
+    }
+    read_contents_length(iterator)?;
+    Ok(ScopedPdu {
+        context_engine_id: extract_octets(iterator)?,
+        context_name: extract_octets(iterator)?,
+        pdu: snmpv1::read_pdu(iterator, limits)?,
+    })
+}
+
+/// The values an agent reports about its SNMP engine, which `Request::discover` learns.
+/// Keeping them in `Request::engine` saves a round-trip for every request after the first.
+#[derive(Debug, Clone)]
+pub struct Engine {
+    id: Vec<u8>,
+    boots: u32,
+    time: u32,
+    discovered_at: Instant,
+}
+
+impl Engine {
+    fn unknown() -> Engine {
+        Engine { id: Vec::new(), boots: 0, time: 0, discovered_at: Instant::now() }
+    }
+
+    /// Returns the ID of the engine.
+    pub fn id(&self) -> &[u8] {
+        &self.id
+    }
+
+    /// Returns how many times the engine has restarted.
+    pub fn boots(&self) -> u32 {
+        self.boots
+    }
+
+    /// Returns the engine time in seconds, estimated by adding the time passed since
+    /// the engine was discovered to the time the agent reported then.
+    pub fn time(&self) -> u32 {
+        self.time.saturating_add(self.discovered_at.elapsed().as_secs() as u32)
+    }
+
+    /// Returns when the engine was discovered, according to the local monotonic clock.
+    pub fn discovered_at(&self) -> Instant {
+        self.discovered_at
+    }
+
+    /// Returns the engine as it should be now, with the estimated time.
+    fn current(&self) -> Engine {
+        Engine { time: self.time(), ..self.clone() }
+    }
+}
+
+/// Keys localized to the engine of an agent.
+struct Keys {
+    authentication: Vec<u8>,
+    privacy: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+/// Contains fields describing a SNMPv3 request as well as functions to send it.
+/// Requests are sent without authentication or privacy (noAuthNoPriv) unless configured otherwise.
+pub struct Request {
+    /// The address to send the request to.
+    pub address: String,
+    /// The port to send the request to if the address does not name one.
+    pub port: u16,
+    /// The OIDs to ask for.
+    pub oids: Vec<Oid>,
+    /// The user to send the request as.
+    pub username: String,
+    /// The message ID to provide. Discovery and every step of a walk are sent with
+    /// message IDs of their own, so late replies to one are not taken for another.
+    pub message_id: u32,
+    /// The request ID to provide.
+    pub request_id: u32,
+    /// How long to wait for a reply, in milliseconds.
+    pub timeout: u64,
+    /// How many times to resend the request if no reply arrives.
+    /// The timeout is doubled for every retry.
+    pub retries: u32,
+    /// How to authenticate the request, if at all.
+    pub authentication: Option<Authentication>,
+    /// How to encrypt the request, if at all. Requires authentication.
+    pub privacy: Option<Privacy>,
+    /// The local address to send the request from. Any address is used if `None`.
+    pub bind_addr: Option<SocketAddr>,
+    /// The engine of the agent, if it is already known. Otherwise every request discovers it first.
+    pub engine: Option<Engine>,
+    /// Limits on what the parser accepts in a response, so a hostile agent can not make it do
+    /// unbounded work. Responses past them fail with `SnmpError::LimitExceeded`.
+    pub decode_limits: DecodeLimits,
+}
+
+impl Request {
+    /// Creates a request for a single OID with only the essential arguments.
+    /// Defaults the message and request IDs to random numbers, timeout to 1000ms, retries to 0,
+    /// the port to 161 and sends the request without authentication.
+    pub fn new<O: Into<Oid>>(address: String, username: String, oid: O) -> Request {
+        Request {
+            address: address,
+            port: snmpv1::DEFAULT_PORT,
+            oids: vec![oid.into()],
+            username: username,
+            message_id: new_message_id(),
+            request_id: rand::random::<u32>(),
+            timeout: 1000,
+            retries: 0,
+            authentication: None,
+            privacy: None,
+            bind_addr: None,
+            engine: None,
+            decode_limits: DecodeLimits::default(),
+        }
+    }
+
+    /// Authenticates the request with the given protocol and password.
+    /// Replies must then be authenticated too, and are rejected if their digest does not match.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv3::{Authentication, Request};
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "usr-md5-none".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+    ///     .with_authentication(Authentication::Md5("authkey1".to_owned()));
+    /// assert!(request.authentication.is_some());
+    /// ```
+    ///
+    /// A reply altered on the way fails with a wrong digest:
+    ///
+    /// ```
+    /// use rust_snmp::snmpv3::{Authentication, Request};
+    /// use rust_snmp::types::{SnmpError, SnmpV3Error};
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// // The agent echoes discovery back, then flips a byte of the signed request before echoing it.
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0u8; 1500];
+    /// #     for tamper in vec![false, true] {
+    /// #         let (length, peer) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         if tamper {
+    /// #             buf[length - 1] ^= 0xff;
+    /// #         }
+    /// #         agent_socket.send_to(&buf[..length], peer).unwrap();
+    /// #     }
+    /// # });
+    /// let request = Request::new(address, "usr-md5-none".to_owned(), vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+    ///     .with_authentication(Authentication::Md5("authkey1".to_owned()));
+    /// match request.send() {
+    ///     Err(SnmpError::ReportError(SnmpV3Error::WrongDigest)) => {},
+    ///     other => panic!("{:?}", other),
+    /// }
+    /// ```
+    pub fn with_authentication(mut self, authentication: Authentication) -> Request {
+        self.authentication = Some(authentication);
+        self
+    }
+
+    /// Encrypts the request with the given protocol and password.
+    /// Privacy requires the request to be authenticated as well.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv3::{Authentication, Privacy, Request};
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "usr-md5-des".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+    ///     .with_authentication(Authentication::Md5("authkey1".to_owned()))
+    ///     .with_privacy(Privacy::Des("privkey1".to_owned()));
+    /// assert!(request.privacy.is_some());
+    /// ```
+    pub fn with_privacy(mut self, privacy: Privacy) -> Request {
+        self.privacy = Some(privacy);
+        self
+    }
+
+    /// Sends the request to the port if the address does not name one, as in a bare hostname.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv3::Request;
+    /// let request = Request::new("192.0.2.1".to_owned(), "usr-none-none".to_owned(), vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+    ///     .with_port(1161);
+    /// assert_eq!(request.port, 1161);
+    /// ```
+    pub fn with_port(mut self, port: u16) -> Request {
+        self.port = port;
+        self
+    }
+
+    /// Sets the limits on what the parser accepts in a response.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv3::Request;
+    /// use rust_snmp::types::DecodeLimits;
+    /// let request = Request::new("192.0.2.1".to_owned(), "usr-none-none".to_owned(), vec![1, 3, 6, 1, 2, 1, 1, 5, 0])
+    ///     .with_decode_limits(DecodeLimits { max_varbinds: 10, ..DecodeLimits::default() });
+    /// assert_eq!(request.decode_limits.max_varbinds, 10);
+    /// ```
+    pub fn with_decode_limits(mut self, limits: DecodeLimits) -> Request {
+        self.decode_limits = limits;
+        self
+    }
+
+    /// Sends the request from the given local address, picking the interface and source port.
+    pub fn with_bind_addr(mut self, bind_addr: SocketAddr) -> Request {
+        self.bind_addr = Some(bind_addr);
+        self
+    }
+
+    /// Sends the request to an agent whose engine is already known, such as from `discover`.
+    pub fn with_engine(mut self, engine: Engine) -> Request {
+        self.engine = Some(engine);
+        self
+    }
+
+    /// Asks the agent for its engine ID, boots and time with an empty request.
+    ///
+    /// Keep the engine in `engine` to skip this for later requests. The agent answers them with
+    /// `SnmpV3Error::NotInTimeWindow` once it restarts or the estimated time drifts too far, and the
+    /// engine then needs to be discovered again.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv3::Request;
+    /// use rust_snmp::types::{SnmpError, SnmpV3Error};
+    /// let mut request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                                "usr-none-none".to_owned(),
+    ///                                vec![1, 3, 6, 1, 2, 1, 1, 3, 0]);
+    /// request.engine = Some(request.discover().unwrap());
+    /// for _ in 0..10 {
+    ///     match request.send() {
+    ///         Ok(message) => println!("{}", message.value()),
+    ///         Err(SnmpError::ReportError(SnmpV3Error::NotInTimeWindow)) =>
+    ///             request.engine = Some(request.discover().unwrap()),
+    ///         Err(e) => println!("{}", e),
+    ///     }
+    /// }
+    /// ```
+    pub fn discover(&self) -> Result<Engine, SnmpError> {
+        let target = snmpv1::resolve(&snmpv1::with_port(&self.address, self.port))?;
+        let socket = snmpv1::connect(self.bind_addr, &target)?;
+        let mut receivepacket = vec![0; SNMP_MAX_MESSAGE_SIZE as usize + 1];
+        self.discover_on(&socket, target, &mut receivepacket)
+    }
+
+    fn discover_on(&self, socket: &UdpSocket, target: SocketAddr, receivepacket: &mut [u8])
+                   -> Result<Engine, SnmpError> {
+        let pdu = Pdu::new(PduType::GetRequest, self.request_id, Vec::new());
+        // The request that follows reuses the message ID, so discovery gets one of its own.
+        let message_id = new_message_id();
+        let sendpacket = self.createpacket(&Engine::unknown(), message_id, &[], &pdu, None);
+        let (engine, _) = snmpv1::exchange(socket, target, &sendpacket, self.retry_policy(), receivepacket, |packet| {
+            locate_truncation(packet, self.parse_header(packet, message_id, None), &self.decode_limits)
+        })?;
+        Ok(engine)
+    }
+
+    /// Discovers the engine of the agent unless it is known, then sends a SNMPv3 GetRequest
+    /// and returns the reply or an error specifying what went wrong.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv3::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "usr-none-none".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1, 5, 0]);
+    /// let message = request.send().unwrap();
+    /// println!("{}", message.to_string().unwrap());
+    /// ```
+    pub fn send(&self) -> Result<Message, SnmpError> {
+        let varbinds = self.oids.iter().map(|oid| (oid.clone(), SnmpType::SnmpNull)).collect();
+        self.send_pdu(PduType::GetRequest, varbinds)
+    }
+
+    /// Sends a SNMPv3 GetNextRequest, which the agent answers with the OID following
+    /// the one asked for and its value.
+    pub fn send_getnext(&self) -> Result<Message, SnmpError> {
+        let varbinds = self.oids.iter().map(|oid| (oid.clone(), SnmpType::SnmpNull)).collect();
+        self.send_pdu(PduType::GetNextRequest, varbinds)
+    }
+
+    /// Sends a SNMPv3 SetRequest assigning the value to every OID of the request.
+    pub fn set(&self, value: SnmpType) -> Result<Message, SnmpError> {
+        let varbinds = self.oids.iter().map(|oid| (oid.clone(), value.clone())).collect();
+        self.send_pdu(PduType::SetRequest, varbinds)
+    }
+
+    /// Walks the subtree below each OID with repeated GetNextRequests, returning every OID and value
+    /// found in them in lexicographic order. The engine is discovered once for the whole walk.
+    ///
+    /// #Examples
+    /// ```no_run
+    /// use rust_snmp::snmpv3::Request;
+    /// let request = Request::new("demo.snmplabs.com:161".to_owned(),
+    ///                            "usr-none-none".to_owned(),
+    ///                            vec![1, 3, 6, 1, 2, 1, 1]);
+    /// for (oid, value) in request.walk().unwrap() {
+    ///     println!("{} = {}", oid, value);
+    /// }
+    /// ```
+    pub fn walk(&self) -> Result<Vec<(Oid, SnmpType)>, SnmpError> {
+        let (socket, target, mut receivepacket, engine) = self.prepare()?;
+        let mut results = Vec::new();
+        for root in &self.oids {
+            let mut current = root.clone();
+            loop {
+                let pdu = Pdu::new(PduType::GetNextRequest, rand::random::<u32>(),
+                                   vec![(current.clone(), SnmpType::SnmpNull)]);
+                let message = self.exchange_pdu(&socket, target, &engine, new_message_id(), &pdu,
+                                                &mut receivepacket)?;
+
+                // Stop at the end of the MIB view or once we leave the subtree.
+                let (oid, data) = message.varbinds().first().cloned().ok_or(SnmpError::ParsingError)?;
+                if let SnmpType::EndOfMibView = data {
+                    break;
+                }
+                if !root.is_prefix_of(&oid) {
+                    break;
+                }
+                if oid <= current {
+                    return Err(SnmpError::OidNotIncreasing(snmpv1::sort_varbinds(results)));
+                }
+                if results.len() >= snmpv1::DEFAULT_MAX_WALK_VARS {
+                    return Err(SnmpError::WalkLimitExceeded(snmpv1::sort_varbinds(results)));
+                }
+                current = oid.clone();
+                results.push((oid, data));
+            }
+        }
+        Ok(snmpv1::sort_varbinds(results))
+    }
+
+    fn send_pdu(&self, pdu_type: PduType, varbinds: Vec<(Oid, SnmpType)>) -> Result<Message, SnmpError> {
+        let (socket, target, mut receivepacket, engine) = self.prepare()?;
+        let pdu = Pdu::new(pdu_type, self.request_id, varbinds);
+        self.exchange_pdu(&socket, target, &engine, self.message_id, &pdu, &mut receivepacket)
+    }
+
+    /// Checks the request and opens a socket to the agent, returning it with the address of the agent,
+    /// a buffer for replies and the engine of the agent.
+    fn prepare(&self) -> Result<(UdpSocket, SocketAddr, Vec<u8>, Engine), SnmpError> {
+        if self.privacy.is_some() && self.authentication.is_none() {
+            return Err(SnmpError::InvalidRequest("privacy requires authentication"));
+        }
+        snmpv1::validate_oids(&self.oids)?;
+
+        let target = snmpv1::resolve(&snmpv1::with_port(&self.address, self.port))?;
+        let socket = snmpv1::connect(self.bind_addr, &target)?;
+        // Make room for the largest message we tell the agent we accept.
+        let mut receivepacket = vec![0; SNMP_MAX_MESSAGE_SIZE as usize + 1];
+
+        // Ask the agent for its engine ID, boots and time, unless we know them already.
+        let engine = match self.engine {
+            Some(ref engine) => engine.current(),
+            None => self.discover_on(&socket, target, &mut receivepacket)?,
+        };
+        Ok((socket, target, receivepacket, engine))
+    }
+
+    /// Sends the PDU secured with keys localized to the engine, and returns the reply.
+    fn exchange_pdu(&self, socket: &UdpSocket, target: SocketAddr, engine: &Engine, message_id: u32, pdu: &Pdu,
+                    receivepacket: &mut [u8]) -> Result<Message, SnmpError> {
+        let keys = self.localize_keys(engine);
+        let sendpacket = self.createpacket(engine, message_id, self.username.as_bytes(), pdu, keys.as_ref());
+        snmpv1::exchange(socket, target, &sendpacket, self.retry_policy(), receivepacket, |packet| {
+            let (_, scoped) = locate_truncation(packet, self.parse_header(packet, message_id, keys.as_ref()),
+                                                &self.decode_limits)?;
+            if scoped.pdu.pdu_type == PduType::Report {
+                return Err(SnmpError::ReportError(parse_report(&scoped.pdu)?));
+            }
+            Message::from_pdu(packet, Vec::new(), scoped.pdu, pdu.request_id)
+        })
+    }
+
+    fn retry_policy(&self) -> snmpv1::RetryPolicy {
+        snmpv1::RetryPolicy { timeout: self.timeout, retries: self.retries, jitter: None }
+    }
+
+    fn localize_keys(&self, engine: &Engine) -> Option<Keys> {
+        self.authentication.as_ref().map(|auth| Keys {
+            authentication: auth.localize_key(&engine.id),
+            privacy: self.privacy.as_ref().map(|privacy| {
+                privacy.protocol().localize_key(auth.protocol(), privacy.password().as_bytes(), &engine.id)
+            }),
+        })
+    }
+
+    fn createpacket(&self, engine: &Engine, message_id: u32, username: &[u8], pdu: &Pdu,
+                    keys: Option<&Keys>) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(250);
+        let authentication = match (keys, self.authentication.as_ref()) {
+            (Some(keys), Some(auth)) => Some((&keys.authentication[..], auth)),
+            _ => None,
+        };
+        let privacy = match (keys.and_then(|keys| keys.privacy.as_ref()), self.privacy.as_ref()) {
+            (Some(key), Some(privacy)) => Some((&key[..], privacy)),
+            _ => None,
+        };
+        let mut flags = SNMP_FLAGS_REPORTABLE;
+        if authentication.is_some() {
+            flags |= SNMP_FLAGS_AUTHENTICATED;
+        }
+        if privacy.is_some() {
+            flags |= SNMP_FLAGS_PRIVATE;
+        }
+
+        // Scoped PDU: context engine ID, context name and the PDU itself
+        let scoped = ScopedPdu {
+            context_engine_id: engine.id.clone(),
+            context_name: Vec::new(),
+            pdu: pdu.clone(),
+        }.encode();
+
+        // Encrypt the scoped PDU into an octet string, sending the salt as privacy parameters.
+        let (mut data, salt) = match privacy {
+            Some((key, privacy)) => {
+                let (mut encrypted, salt) = privacy.protocol().encrypt(key, engine, scoped);
+                let mut data = vec![0x04];
+                write_length(encrypted.len(), &mut data);
+                data.append(&mut encrypted);
+                (data, salt)
+            }
+            None => (scoped, Vec::new()),
+        };
+        let data_length = data.len();
+
+        // Global data: message ID, max message size, flags and security model
+        let mut global = Vec::with_capacity(20);
+        global.append(&mut message_id.encode_snmp());
+        global.append(&mut SNMP_MAX_MESSAGE_SIZE.encode_snmp());
+        global.append(&mut [flags][..].encode_snmp());
+        global.append(&mut SNMP_USM_SECURITY_MODEL.encode_snmp());
+
+        // Security parameters
+        let mut security = Vec::with_capacity(40 + username.len());
+        security.append(&mut engine.id.encode_snmp());  // Authoritative Engine ID
+        security.append(&mut engine.boots.encode_snmp());     // Authoritative Engine Boots
+        security.append(&mut engine.time.encode_snmp());      // Authoritative Engine Time
+        security.append(&mut username.encode_snmp());        // Username
+        let digest_length = authentication.map_or(0, |(_, auth)| auth.protocol().digest_length());
+        security.append(&mut vec![0u8; digest_length].encode_snmp());  // Authentication Parameters
+        let mut privacy = salt.encode_snmp();                 // Privacy Parameters
+        let privacy_length = privacy.len();
+        security.append(&mut privacy);
+        let mut parameters = vec![0x30];
+        write_length(security.len(), &mut parameters);
+        parameters.append(&mut security);
+
+        // SNMP version
+        let mut message = Vec::with_capacity(250);
+        message.append(&mut SNMP_VERSION_3.encode_snmp());
+
+        // Global data
+        message.push(0x30);
+        write_length(global.len(), &mut message);
+        message.append(&mut global);
+
+        // Security parameters, as a sequence wrapped in an octet string
+        message.append(&mut parameters.encode_snmp());
+
+        // Scoped PDU, encrypted or not
+        message.append(&mut data);
+
+        // SNMP sequence
+        buf.push(0x30);
+        write_length(message.len(), &mut buf);
+        buf.append(&mut message);
+
+        // Sign the whole message, then splice the digest in over the zeroed placeholder
+        // which sits right before the privacy parameters and the scoped PDU.
+        if let Some((key, auth)) = authentication {
+            let digest = auth.protocol().digest(key, &buf);
+            let end = buf.len() - data_length - privacy_length;
+            buf[end - digest_length..end].copy_from_slice(&digest);
+        }
+        buf
+    }
+
+    /// Parses everything up to the PDU of a response, decrypting it if needed.
+    /// Returns the engine of the agent and the PDU.
+    fn parse_header(&self, packet: &[u8], message_id: u32, keys: Option<&Keys>)
+                    -> Result<(Engine, ScopedPdu), SnmpError> {
+        let mut iterator = packet.iter();
+
+        // Confirm that the first bit is the SNMP flag.
+        if next_byte(&mut iterator)? != 0x30 {
+            return Err(SnmpError::ParsingError);
+        }
+
+        // Check that the packet is as long as it needs to be.
+        if read_contents_length(&mut iterator)? != iterator.len() {
+            return Err(SnmpError::ParsingError);
+        }
+
+        // Confirm the protocol is SNMPv3.
+        match extract_value(&mut iterator)? {
+            SnmpType::SnmpInteger(i) => if i != SNMP_VERSION_3 as i64 {
+                return Err(SnmpError::ParsingError);
+            },
+            _ => return Err(SnmpError::ParsingError),
+        };
+
+        // Global data sequence
+        if next_byte(&mut iterator)? != 0x30 {
+            return Err(SnmpError::ParsingError);
+        }
+        read_contents_length(&mut iterator)?;
+
+        // Get the message ID, and make sure it is the reply to our message.
+        match extract_value(&mut iterator)? {
+            SnmpType::SnmpInteger(i) => if i as u32 != message_id {
+                return Err(SnmpError::RequestIdMismatch);
+            },
+            _ => return Err(SnmpError::ParsingError),
+        };
+
+        // Skip past the max message size, read the flags and skip past the security model.
+        extract_value(&mut iterator)?;
+        let flags = extract_octets(&mut iterator)?;
+        extract_value(&mut iterator)?;
+
+        // Get the engine, the authentication parameters and the salt from the security parameters.
+        let parameters = extract_octets(&mut iterator)?;
+        let parameters_start = packet.len() - iterator.len() - parameters.len();
+        let (engine, digest, salt) = parse_security_parameters(&parameters)?;
+        let flags = flags.first().cloned().unwrap_or(0);
+
+        // Check the digest of an authenticated reply against the packet signed with the digest zeroed.
+        if flags & SNMP_FLAGS_AUTHENTICATED != 0 {
+            if let (Some(keys), Some(auth)) = (keys, self.authentication.as_ref()) {
+                let mut unsigned = packet.to_vec();
+                let start = parameters_start + digest.start;
+                for byte in &mut unsigned[start..start + digest.len()] {
+                    *byte = 0;
+                }
+                if auth.protocol().digest(&keys.authentication, &unsigned) != parameters[digest] {
+                    return Err(SnmpError::ReportError(SnmpV3Error::WrongDigest));
+                }
+            }
+        }
+
+        // Decrypt the scoped PDU if the agent encrypted it.
+        let scoped = if flags & SNMP_FLAGS_PRIVATE != 0 {
+            let key = keys.and_then(|keys| keys.privacy.as_ref());
+            match (key, self.privacy.as_ref()) {
+                (Some(key), Some(privacy)) => {
+                    privacy.protocol().decrypt(key, &engine, &salt, extract_octets(&mut iterator)?)?
+                },
+                _ => return Err(SnmpError::ParsingError),
+            }
         } else {
-            mib[counter] = *mibval as u8;
-            counter += 1;
-        }
-    }
-    let mib = &mib[0..miblen];
-    let miblen = miblen;
-    let snmplen = 29 + community.len() + miblen - 1;*/
-
-    // SNMP sequence start
-    buf[0] = 0x30;
-    
-    // SNMP version
-    let mut index = 2;
-    index += types::write_u8(&mut buf[index..], 0x03);
-
-    buf[index] = 0x30; // Sequence
-    buf[index+1] = 0x11; // Length
-    index += 2;
-
-    // Message ID
-    index += types::write_i32(&mut buf[index..], 0x009E5D19);
-    
-    // Max message size
-    index += types::write_i24(&mut buf[index..], 0x00FFE3);
-
-    // Message flags: reportable, not encrypted or authenticated
-    index += types::write_octet_string(&mut buf[index..], &[0b0000_0100]);
-    
-    // Security model
-    index += types::write_u8(&mut buf[index..], 0x03);
-    
-    // Security parameters
-    //index += types::write_octet_string(&mut buf[index..], &[0x03]);
-    
-    // UNKNWN
-    index += types::write_raw_octets(&mut buf[index..], &[0x04, 0x2D, 0x30, 0x2B, 0x04, 0x0E]);
-
-    // Engine ID
-    index += types::write_raw_octets(&mut buf[index..], &[0x80, 0x00, 0x4f, 0xb8, 0x05,
-                                                            0x63, 0x6c, 0x6f, 0x75, 0x64,
-                                                            0x4d, 0xab, 0x22, 0xcc]);
-    
-    // Authoritative Engine Boots
-    index += types::write_u8(&mut buf[index..], 0x00);
-
-    // Authoritative Engine Time
-    index += types::write_u8(&mut buf[index..], 0x00);
-    
-    // Username
-    index += types::write_octet_string(&mut buf[index..], "usr-none-none".as_bytes());
-    
-    // Authentication Parameters
-    index += types::write_octet_string(&mut buf[index..], &[]);
-    
-    // Privacy Parameters
-    index += types::write_octet_string(&mut buf[index..], &[]);
-    
-    // Start sequence
-    buf[index]   = 0x30; // Sequence
-    buf[index+1] = 0x21; // Length
-    index += 2;
-    
-    // Context Engine ID
-    index += types::write_raw_octets(&mut buf[index..], &[0x80, 0x00, 0x4f, 0xb8, 0x05,
-                                                            0x63, 0x6c, 0x6f, 0x75, 0x64,
-                                                            0x4d, 0xab, 0x22, 0xcc]);
-
-    // Context name
-    index += types::write_octet_string(&mut buf[index..], &[]);
-
-    buf[index]   = 0xA0; // GetRequest PDU
-    buf[index+1] = 0x0E; // Length
-
-    // Request ID
-    index += types::write_i32(&mut buf[index..], 0x2C180DBB);
-    
-    // Error status and ID
-    index += types::write_u8(&mut buf[index..], 0x00);
-    index += types::write_u8(&mut buf[index..], 0x00);
-
-    // Variable bindings
-    buf[index+3] = 0x30; // Sequence
-    buf[index+4] = 0x00; // Length
-
-    // Packet length
-    buf[1] = (index-3) as u8;
-
-    return;
-
-    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
-    socket.set_read_timeout(Some(time::Duration::from_millis(1000))).unwrap();
-    socket.send_to(&buf[0..index], addr).unwrap();
-    
-    let mut packet: [u8; 1024] = [0; 1024];
-    let (length, _) = socket.recv_from(&mut packet).unwrap();
-    for i in 0..length {
-        print!("{} ", packet[i]);
-    }        
+            iterator.as_slice().to_vec()
+        };
+        let scoped = ScopedPdu::decode_with_limits(&scoped, &self.decode_limits)?;
+
+        // Anything but a report must come back at the security level it was sent at.
+        // Agents send some reports, such as unknown user names, without authentication.
+        if keys.is_some() && scoped.pdu.pdu_type != PduType::Report {
+            let mut expected = SNMP_FLAGS_AUTHENTICATED;
+            if self.privacy.is_some() {
+                expected |= SNMP_FLAGS_PRIVATE;
+            }
+            if flags & expected != expected {
+                return Err(SnmpError::ParsingError);
+            }
+        }
+        Ok((engine, scoped))
+    }
+}
+
+/// Picks a random message ID. Message IDs must be positive 32-bit integers.
+fn new_message_id() -> u32 {
+    rand::random::<u32>() >> 1
+}
+
+/// Reads the problem a Report PDU identifies by the OID of its first variable binding.
+fn parse_report(pdu: &Pdu) -> Result<SnmpV3Error, SnmpError> {
+    match pdu.varbinds.first() {
+        Some((oid, _)) => Ok(oid.clone().into()),
+        None => Err(SnmpError::ParsingError),
+    }
+}
+
+/// Parses the security parameters, returning the engine of the agent, where the authentication
+/// parameters lie within the security parameters, and the privacy parameters.
+fn parse_security_parameters(parameters: &[u8]) -> Result<(Engine, Range<usize>, Vec<u8>), SnmpError> {
+    let mut iterator = parameters.iter();
+    if next_byte(&mut iterator)? != 0x30 {
+        return Err(SnmpError::ParsingError);
+    }
+    read_contents_length(&mut iterator)?;
+
+    let id = extract_octets(&mut iterator)?;
+    let boots = match extract_value(&mut iterator)? {
+        SnmpType::SnmpInteger(i) => i as u32,
+        _ => return Err(SnmpError::ParsingError),
+    };
+    let time = match extract_value(&mut iterator)? {
+        SnmpType::SnmpInteger(i) => i as u32,
+        _ => return Err(SnmpError::ParsingError),
+    };
+
+    // Skip past the username, and note where the authentication parameters end.
+    extract_octets(&mut iterator)?;
+    let digest_length = extract_octets(&mut iterator)?.len();
+    let digest_end = parameters.len() - iterator.len();
+    let salt = extract_octets(&mut iterator)?;
+
+    let engine = Engine { id: id, boots: boots, time: time, discovered_at: Instant::now() };
+    Ok((engine, digest_end - digest_length..digest_end, salt))
+}
+
+/// Sends a SNMPv3 GetRequest as the given user, without authentication or privacy,
+/// and returns the reply or an error specifiying what went wrong.
+///
+/// #Examples
+/// ```no_run
+/// let message = rust_snmp::snmpv3::smtpv3_send("demo.snmplabs.com:161",
+///                                              "usr-none-none",
+///                                              &[1, 3, 6, 1, 2, 1, 1, 5, 0]).unwrap();
+/// ```
+pub fn smtpv3_send(addr: &str,
+                   username: &str,
+                   mibvals: &[u32]) -> Result<Message, SnmpError> {
+    Request::new(addr.to_owned(), username.to_owned(), mibvals).send()
 }
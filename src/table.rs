@@ -0,0 +1,83 @@
+//! Contains a helper for putting the rows of a walked table back together.
+
+use std::collections::BTreeMap;
+use types::*;
+
+/// The rows of a table, assembled from the results of a walk by the index at the end of each OID.
+///
+/// Each OID below the entry of a table is the entry, then the column, then the index of the row,
+/// such as ifDescr.2 being `1.3.6.1.2.1.2.2.1` `.2` `.2`. Indices may be more than one arc long,
+/// as in tables indexed by an IP address.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::table::Table;
+/// use rust_snmp::types::{Oid, SnmpType};
+///
+/// // What a walk of ifTable might return, for the ifDescr and ifType columns.
+/// let if_entry: Oid = vec![1, 3, 6, 1, 2, 1, 2, 2, 1].into();
+/// let walked = vec![
+///     (vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 2, 1].into(), SnmpType::SnmpString("lo".to_owned())),
+///     (vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 2, 2].into(), SnmpType::SnmpString("eth0".to_owned())),
+///     (vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 3, 1].into(), SnmpType::SnmpInteger(24)),
+///     (vec![1, 3, 6, 1, 2, 1, 2, 2, 1, 3, 2].into(), SnmpType::SnmpInteger(6)),
+///     // Anything outside the table is left out.
+///     (vec![1, 3, 6, 1, 2, 1, 2, 1, 0].into(), SnmpType::SnmpInteger(2)),
+/// ];
+///
+/// let table = Table::new(&if_entry, walked);
+/// assert_eq!(table.len(), 2);
+/// let eth0: Oid = vec![2].into();
+/// assert_eq!(table.get(&eth0, 2).and_then(|value| value.as_str()), Some("eth0"));
+/// assert_eq!(table.get(&eth0, 3).and_then(|value| value.as_integer()), Some(6));
+///
+/// for (index, row) in table.rows() {
+///     println!("{}: {} columns", index, row.len());
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    rows: BTreeMap<Oid, BTreeMap<u32, SnmpType>>,
+}
+
+impl Table {
+    /// Groups the variables below the entry OID of a table, such as ifEntry, into rows
+    /// mapping each column number to its value. Variables outside the table are left out.
+    pub fn new(entry: &Oid, varbinds: Vec<(Oid, SnmpType)>) -> Table {
+        let mut rows: BTreeMap<Oid, BTreeMap<u32, SnmpType>> = BTreeMap::new();
+        for (oid, value) in varbinds {
+            if !entry.is_prefix_of(&oid) || oid.arcs().len() < entry.arcs().len() + 2 {
+                continue;
+            }
+            let column = oid.arcs()[entry.arcs().len()];
+            let index: Oid = oid.arcs()[entry.arcs().len() + 1..].into();
+            rows.entry(index).or_default().insert(column, value);
+        }
+        Table { rows: rows }
+    }
+
+    /// Returns the rows in the order of their indices, each mapping column numbers to values.
+    pub fn rows(&self) -> impl Iterator<Item = (&Oid, &BTreeMap<u32, SnmpType>)> {
+        self.rows.iter()
+    }
+
+    /// Returns the row with the index, if the table has it.
+    pub fn row(&self, index: &Oid) -> Option<&BTreeMap<u32, SnmpType>> {
+        self.rows.get(index)
+    }
+
+    /// Returns the value in the column of the row with the index, if the table has it.
+    pub fn get(&self, index: &Oid, column: u32) -> Option<&SnmpType> {
+        self.rows.get(index).and_then(|row| row.get(&column))
+    }
+
+    /// Returns how many rows the table has.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns true if the table has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
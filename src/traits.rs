@@ -1,4 +1,5 @@
 use byteorder::{BigEndian, ByteOrder};
+use std::net::Ipv4Addr;
 use types::*;
 
 pub(crate) trait EncodeSnmp {
@@ -9,67 +10,336 @@ pub(crate) trait DecodeSnmp {
     fn decode_snmp(&[u8]) -> Result<Self, SnmpError> where Self: Sized;
 }
 
+/// Encodes a value into a fixed buffer instead of allocating, for the same bytes as `EncodeSnmp`.
+pub(crate) trait WriteSnmp {
+    fn write_snmp(&self, writer: &mut BufferWriter) -> Result<(), SnmpError>;
+}
+
+/// Writes BER values into a fixed buffer from its end towards its start, so the length
+/// of every value is known by the time its tag and length are written in front of it.
+pub(crate) struct BufferWriter<'a> {
+    buf: &'a mut [u8],
+    start: usize,
+}
+
+impl<'a> BufferWriter<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> BufferWriter<'a> {
+        let start = buf.len();
+        BufferWriter { buf: buf, start: start }
+    }
+
+    /// Returns how many bytes have been written so far.
+    pub(crate) fn len(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
+    /// Writes the bytes in front of everything written so far.
+    pub(crate) fn prepend(&mut self, bytes: &[u8]) -> Result<(), SnmpError> {
+        if bytes.len() > self.start {
+            return Err(SnmpError::BufferTooSmall);
+        }
+        self.start -= bytes.len();
+        self.buf[self.start..self.start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Writes the tag and the length of everything written since `end`, which was
+    /// the length of the writer before the contents were written.
+    pub(crate) fn prepend_header(&mut self, tag: u8, end: usize) -> Result<(), SnmpError> {
+        let length = self.len() - end;
+        if length < 0x80 {
+            return self.prepend(&[tag, length as u8]);
+        }
+        let mut values: [u8;10] = [0;10];
+        BigEndian::write_u64(&mut values[2..], length as u64);
+        let start = values[2..].iter().position(|b| *b != 0).unwrap_or(7);
+        values[start] = tag;
+        values[start + 1] = 0x80 | (8 - start) as u8;
+        self.prepend(&values[start..])
+    }
+
+    /// Moves the written bytes to the start of the buffer, returning their length.
+    pub(crate) fn finish(self) -> usize {
+        let length = self.len();
+        self.buf.copy_within(self.start.., 0);
+        length
+    }
+}
+
+/// Appends a BER length, using the long form for lengths above 127.
+pub(crate) fn write_length(length: usize, buf: &mut Vec<u8>) {
+    if length < 0x80 {
+        buf.push(length as u8);
+        return;
+    }
+
+    // The long form starts with the number of bytes needed to hold the length.
+    let mut values: [u8;8] = [0;8];
+    BigEndian::write_u64(&mut values, length as u64);
+    let start = values.iter().position(|b| *b != 0).unwrap_or(7);
+    buf.push(0x80 | (8 - start) as u8);
+    buf.extend(&values[start..]);
+}
+
+/// Appends an OID sub-identifier in base-128, setting the continuation bit
+/// on every byte except the last.
+pub(crate) fn encode_subidentifier(value: u32, buf: &mut Vec<u8>) {
+    let mut shift = 28;
+    while shift > 0 && value >> shift == 0 {
+        shift -= 7;
+    }
+    while shift > 0 {
+        buf.push(0x80 | ((value >> shift) & 0x7F) as u8);
+        shift -= 7;
+    }
+    buf.push((value & 0x7F) as u8);
+}
+
 impl EncodeSnmp for u8 {
     fn encode_snmp(&self) -> Vec<u8> {
-        vec![
-            0x02, // Integer type
-            0x01, // Length
-            *self // Value
-        ]        
-    }    
+        encode_unsigned(0x02, *self as u64)
+    }
 }
 
 impl EncodeSnmp for i16 {
     fn encode_snmp(&self) -> Vec<u8> {
-        let mut values: [u8;2] = [0;2];
-        BigEndian::write_i16(&mut values, *self);
-        vec![
-            0x02, // Integer type
-            0x02, // Length
-            values[0],
-            values[1]
-        ]        
-    }    
+        (*self as i64).encode_snmp()
+    }
 }
 
 impl EncodeSnmp for i32 {
     fn encode_snmp(&self) -> Vec<u8> {
-        let mut values: [u8;4] = [0;4];
-        BigEndian::write_i32(&mut values, *self);
-        vec![
-            0x02, // Integer type
-            0x04, // Length
-            values[0],
-            values[1],
-            values[2],
-            values[3]
-        ]        
-    }    
+        (*self as i64).encode_snmp()
+    }
 }
 
 impl EncodeSnmp for u32 {
     fn encode_snmp(&self) -> Vec<u8> {
-        let mut values: [u8;4] = [0;4];
-        BigEndian::write_u32(&mut values, *self);
-        vec![
-            0x02, // Integer type
-            0x04, // Length
-            values[0],
-            values[1],
-            values[2],
-            values[3]
-        ]        
-    }    
+        // Values with the high bit set get a leading zero byte, so they are not read as negative.
+        encode_unsigned(0x02, *self as u64)
+    }
+}
+
+/// Returns where a big-endian two's complement integer starts once the leading
+/// bytes that only repeat the sign are dropped.
+fn integer_start(bytes: &[u8]) -> usize {
+    let mut start = 0;
+    while start + 1 < bytes.len() &&
+          ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0) ||
+           (bytes[start] == 0xFF && bytes[start + 1] & 0x80 != 0)) {
+        start += 1;
+    }
+    start
+}
+
+/// Encodes a big-endian two's complement integer under the given tag,
+/// dropping leading bytes that only repeat the sign.
+fn encode_integer(tag: u8, bytes: &[u8]) -> Vec<u8> {
+    let start = integer_start(bytes);
+    let mut values = vec![tag, (bytes.len() - start) as u8];
+    values.extend(&bytes[start..]);
+    values
+}
+
+/// Encodes an unsigned number under the given application tag.
+fn encode_unsigned(tag: u8, value: u64) -> Vec<u8> {
+    // Leave a leading zero byte so the value is never read as negative.
+    let mut values: [u8;9] = [0;9];
+    BigEndian::write_u64(&mut values[1..], value);
+    encode_integer(tag, &values)
+}
+
+impl EncodeSnmp for i64 {
+    fn encode_snmp(&self) -> Vec<u8> {
+        let mut values: [u8;8] = [0;8];
+        BigEndian::write_i64(&mut values, *self);
+        encode_integer(0x02, &values)
+    }
 }
 
 impl EncodeSnmp for [u8] {
     fn encode_snmp(&self) -> Vec<u8> {
-        let mut values = vec![0x04, self.len() as u8];
-        values.extend(self);
-        values
+        encode_octets(0x04, self)
     }    
 }
 
+/// Encodes the bytes as they are under the given tag.
+fn encode_octets(tag: u8, bytes: &[u8]) -> Vec<u8> {
+    let mut values = vec![tag];
+    write_length(bytes.len(), &mut values);
+    values.extend(bytes);
+    values
+}
+
+/// Returns the first sub-identifier of an OID, which holds its first two arcs as (first * 40) + second.
+/// OIDs that fail `Oid::validate` are encoded as best they can be.
+fn first_subidentifier(oid: &Oid) -> u32 {
+    let first = oid.arcs().first().cloned().unwrap_or(0);
+    let second = oid.arcs().get(1).cloned().unwrap_or(0);
+    first.saturating_mul(40).saturating_add(second)
+}
+
+impl EncodeSnmp for Oid {
+    fn encode_snmp(&self) -> Vec<u8> {
+        let mut mib = Vec::with_capacity(self.arcs().len() + 4);
+        encode_subidentifier(first_subidentifier(self), &mut mib);
+        for arc in self.arcs().iter().skip(2) {
+            encode_subidentifier(*arc, &mut mib);
+        }
+        let mut values = vec![0x06];
+        write_length(mib.len(), &mut values);
+        values.append(&mut mib);
+        values
+    }
+}
+
+/// Writes a big-endian two's complement integer under the given tag,
+/// dropping leading bytes that only repeat the sign.
+fn write_integer(tag: u8, bytes: &[u8], writer: &mut BufferWriter) -> Result<(), SnmpError> {
+    let start = integer_start(bytes);
+    writer.prepend(&bytes[start..])?;
+    writer.prepend(&[tag, (bytes.len() - start) as u8])
+}
+
+/// Writes an unsigned number under the given application tag.
+fn write_unsigned(tag: u8, value: u64, writer: &mut BufferWriter) -> Result<(), SnmpError> {
+    let mut values: [u8;9] = [0;9];
+    BigEndian::write_u64(&mut values[1..], value);
+    write_integer(tag, &values, writer)
+}
+
+impl WriteSnmp for u32 {
+    fn write_snmp(&self, writer: &mut BufferWriter) -> Result<(), SnmpError> {
+        write_unsigned(0x02, *self as u64, writer)
+    }
+}
+
+impl WriteSnmp for i64 {
+    fn write_snmp(&self, writer: &mut BufferWriter) -> Result<(), SnmpError> {
+        let mut values: [u8;8] = [0;8];
+        BigEndian::write_i64(&mut values, *self);
+        write_integer(0x02, &values, writer)
+    }
+}
+
+impl WriteSnmp for [u8] {
+    fn write_snmp(&self, writer: &mut BufferWriter) -> Result<(), SnmpError> {
+        write_octets(0x04, self, writer)
+    }
+}
+
+/// Writes the bytes as they are under the given tag.
+fn write_octets(tag: u8, bytes: &[u8], writer: &mut BufferWriter) -> Result<(), SnmpError> {
+    let end = writer.len();
+    writer.prepend(bytes)?;
+    writer.prepend_header(tag, end)
+}
+
+/// Writes an OID sub-identifier backwards, its last byte first.
+fn prepend_subidentifier(value: u32, writer: &mut BufferWriter) -> Result<(), SnmpError> {
+    let mut value = value;
+    writer.prepend(&[(value & 0x7F) as u8])?;
+    value >>= 7;
+    while value != 0 {
+        writer.prepend(&[0x80 | (value & 0x7F) as u8])?;
+        value >>= 7;
+    }
+    Ok(())
+}
+
+impl WriteSnmp for Oid {
+    fn write_snmp(&self, writer: &mut BufferWriter) -> Result<(), SnmpError> {
+        let end = writer.len();
+        for arc in self.arcs().iter().skip(2).rev() {
+            prepend_subidentifier(*arc, writer)?;
+        }
+        prepend_subidentifier(first_subidentifier(self), writer)?;
+        writer.prepend_header(0x06, end)
+    }
+}
+
+impl WriteSnmp for SnmpType {
+    fn write_snmp(&self, writer: &mut BufferWriter) -> Result<(), SnmpError> {
+        match *self {
+            SnmpType::SnmpInteger(ref i) => i.write_snmp(writer),
+            SnmpType::SnmpString(ref s) => s.as_bytes().write_snmp(writer),
+            SnmpType::SnmpOctetString(ref bytes) => bytes.write_snmp(writer),
+            SnmpType::SnmpNull => writer.prepend(&[0x05, 0x00]),
+            SnmpType::SnmpObjectID(ref o) => o.write_snmp(writer),
+            SnmpType::SnmpIpAddress(ref ip) => {
+                writer.prepend(&ip.octets())?;
+                writer.prepend(&[0x40, 0x04])
+            },
+            SnmpType::SnmpCounter32(c) => write_unsigned(0x41, c as u64, writer),
+            SnmpType::SnmpGauge32(g) => write_unsigned(0x42, g as u64, writer),
+            SnmpType::SnmpTimeTicks(t) => write_unsigned(0x43, t as u64, writer),
+            SnmpType::SnmpOpaque(ref bytes) => write_octets(0x44, bytes, writer),
+            SnmpType::SnmpCounter64(c) => write_unsigned(0x46, c, writer),
+            SnmpType::NoSuchObject => writer.prepend(&[0x80, 0x00]),
+            SnmpType::NoSuchInstance => writer.prepend(&[0x81, 0x00]),
+            SnmpType::EndOfMibView => writer.prepend(&[0x82, 0x00]),
+            SnmpType::SnmpSequence(ref values) => {
+                let end = writer.len();
+                for value in values.iter().rev() {
+                    value.write_snmp(writer)?;
+                }
+                writer.prepend_header(0x30, end)
+            },
+        }
+    }
+}
+
+impl EncodeSnmp for Ipv4Addr {
+    fn encode_snmp(&self) -> Vec<u8> {
+        let mut values = vec![0x40, 0x04];
+        values.extend(&self.octets());
+        values
+    }
+}
+
+impl EncodeSnmp for Counter32 {
+    fn encode_snmp(&self) -> Vec<u8> {
+        encode_unsigned(0x41, self.0 as u64)
+    }
+}
+
+impl EncodeSnmp for Gauge32 {
+    fn encode_snmp(&self) -> Vec<u8> {
+        encode_unsigned(0x42, self.0 as u64)
+    }
+}
+
+impl EncodeSnmp for TimeTicks {
+    fn encode_snmp(&self) -> Vec<u8> {
+        encode_unsigned(0x43, self.0 as u64)
+    }
+}
+
+impl EncodeSnmp for SnmpType {
+    fn encode_snmp(&self) -> Vec<u8> {
+        match *self {
+            SnmpType::SnmpInteger(ref i) => i.encode_snmp(),
+            SnmpType::SnmpString(ref s) => s.as_bytes().encode_snmp(),
+            SnmpType::SnmpOctetString(ref bytes) => bytes.encode_snmp(),
+            SnmpType::SnmpNull => vec![0x05, 0x00],
+            SnmpType::SnmpObjectID(ref o) => o.encode_snmp(),
+            SnmpType::SnmpIpAddress(ref ip) => ip.encode_snmp(),
+            SnmpType::SnmpCounter32(ref c) => Counter32(*c).encode_snmp(),
+            SnmpType::SnmpGauge32(ref g) => Gauge32(*g).encode_snmp(),
+            SnmpType::SnmpTimeTicks(ref t) => TimeTicks(*t).encode_snmp(),
+            SnmpType::SnmpOpaque(ref bytes) => encode_octets(0x44, bytes),
+            SnmpType::SnmpCounter64(ref c) => encode_unsigned(0x46, *c),
+            SnmpType::NoSuchObject => vec![0x80, 0x00],
+            SnmpType::NoSuchInstance => vec![0x81, 0x00],
+            SnmpType::EndOfMibView => vec![0x82, 0x00],
+            SnmpType::SnmpSequence(ref values) => {
+                let contents: Vec<u8> = values.iter().flat_map(|value| value.encode_snmp()).collect();
+                encode_octets(0x30, &contents)
+            },
+        }
+    }
+}
+
 impl DecodeSnmp for i64 {
     fn decode_snmp(data: &[u8]) -> Result<Self, SnmpError> {
         if data.len() > 8 || data.len() < 1 { return Err(SnmpError::ParsingError) };
@@ -77,6 +347,63 @@ impl DecodeSnmp for i64 {
     }
 }
 
+impl DecodeSnmp for u32 {
+    fn decode_snmp(data: &[u8]) -> Result<Self, SnmpError> {
+        // Unsigned values may carry a leading zero byte to keep the high bit clear.
+        if data.len() > 5 || data.is_empty() { return Err(SnmpError::ParsingError) };
+        let value = BigEndian::read_uint(data, data.len());
+        if value > u32::MAX as u64 { return Err(SnmpError::ParsingError) };
+        Ok(value as u32)
+    }
+}
+
+impl DecodeSnmp for u64 {
+    fn decode_snmp(data: &[u8]) -> Result<Self, SnmpError> {
+        // A ninth byte is only allowed as a leading zero keeping the high bit clear.
+        if data.len() == 9 && data[0] == 0 { return Ok(BigEndian::read_u64(&data[1..])) };
+        if data.len() > 8 || data.is_empty() { return Err(SnmpError::ParsingError) };
+        Ok(BigEndian::read_uint(data, data.len()))
+    }
+}
+
+impl DecodeSnmp for Oid {
+    fn decode_snmp(data: &[u8]) -> Result<Self, SnmpError> {
+        // The last byte of a sub-identifier is the only one without the continuation bit.
+        if data.last().map_or(true, |b| b & 0x80 != 0) { return Err(SnmpError::ParsingError) };
+
+        let mut subids = Vec::with_capacity(data.len());
+        let mut value: u32 = 0;
+        for byte in data {
+            if value > u32::MAX >> 7 { return Err(SnmpError::ParsingError) };
+            value = (value << 7) | (*byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                subids.push(value);
+                value = 0;
+            }
+        }
+
+        // The first sub-identifier holds the first two arcs as (first * 40) + second.
+        let (first, second) = match subids[0] {
+            n if n < 40 => (0, n),
+            n if n < 80 => (1, n - 40),
+            n => (2, n - 80),
+        };
+
+        let mut arcs = Vec::with_capacity(subids.len() + 1);
+        arcs.push(first);
+        arcs.push(second);
+        arcs.extend(subids.into_iter().skip(1));
+        Ok(Oid::from(arcs))
+    }
+}
+
+impl DecodeSnmp for Ipv4Addr {
+    fn decode_snmp(data: &[u8]) -> Result<Self, SnmpError> {
+        if data.len() != 4 { return Err(SnmpError::ParsingError) };
+        Ok(Ipv4Addr::new(data[0], data[1], data[2], data[3]))
+    }
+}
+
 impl DecodeSnmp for String {
     fn decode_snmp(data: &[u8]) -> Result<Self, SnmpError> {
         Ok(String::from_utf8(data.to_vec())?)
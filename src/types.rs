@@ -1,44 +1,1151 @@
 //! Contains various associated types for the library.
 
-use std::{io, string, slice};
+use std::{error, fmt, io, string, slice};
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+use byteorder::{BigEndian, ByteOrder};
 use traits::*;
 
 const SNMP_INTEGER_CODE: u8      = 0x02;
 const SNMP_OCTET_STRING_CODE: u8 = 0x04;
 const SNMP_NULL_CODE: u8         = 0x05;
+const SNMP_OBJECT_ID_CODE: u8    = 0x06;
+const SNMP_IPADDRESS_CODE: u8    = 0x40;
+const SNMP_COUNTER32_CODE: u8    = 0x41;
+const SNMP_GAUGE32_CODE: u8      = 0x42;
+const SNMP_TIMETICKS_CODE: u8    = 0x43;
+const SNMP_OPAQUE_CODE: u8       = 0x44;
+const SNMP_COUNTER64_CODE: u8    = 0x46;
+const SNMP_NO_SUCH_OBJECT_CODE: u8   = 0x80;
+const SNMP_NO_SUCH_INSTANCE_CODE: u8 = 0x81;
+const SNMP_END_OF_MIB_VIEW_CODE: u8  = 0x82;
 
 /// Enum containing the various SNMP datatypes.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SnmpType {
     /// An integer.
     SnmpInteger(i64),
-    /// An octet string.
+    /// An octet string holding UTF-8 text.
     SnmpString(String),
+    /// An octet string that is not valid UTF-8, such as a MAC address.
+    SnmpOctetString(Vec<u8>),
     /// Null.
     SnmpNull,
-    /// An OID.
-    SnmpObjectID(Vec<u8>),
-    // A sequence of some sort
-    //SnmpSequence(Vec<SnmpType>),
+    /// An OID, as its numeric arcs.
+    SnmpObjectID(Oid),
+    /// An IPv4 address.
+    SnmpIpAddress(Ipv4Addr),
+    /// A 32-bit counter that wraps around when it reaches its maximum.
+    SnmpCounter32(u32),
+    /// A 32-bit gauge that may increase or decrease, but never wraps.
+    SnmpGauge32(u32),
+    /// Time in hundredths of a second.
+    SnmpTimeTicks(u32),
+    /// Opaque data, usually another BER value wrapped for managers that do not know its type.
+    /// Vendors use it for floating-point values, which `as_f64` decodes.
+    SnmpOpaque(Vec<u8>),
+    /// A 64-bit counter, used for high-capacity interface counters.
+    SnmpCounter64(u64),
+    /// The agent does not implement the object asked for. SNMPv2c only.
+    NoSuchObject,
+    /// The object exists, but has no instance with the OID asked for. SNMPv2c only.
+    NoSuchInstance,
+    /// There are no OIDs after the one asked for. SNMPv2c only.
+    EndOfMibView,
+    /// A sequence of values, such as a variable binding.
+    SnmpSequence(Vec<SnmpType>),
+}
+
+impl SnmpType {
+    /// Returns the number if this is a SnmpInteger.
+    pub fn as_integer(&self) -> Option<i64> {
+        match *self {
+            SnmpType::SnmpInteger(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Returns the string if this is a SnmpString.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            SnmpType::SnmpString(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw octets if this is a SnmpString or SnmpOctetString.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match *self {
+            SnmpType::SnmpString(ref s) => Some(s.as_bytes()),
+            SnmpType::SnmpOctetString(ref bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the numbers of the set bits if this is a SnmpString or SnmpOctetString holding BITS,
+    /// where bit 0 is the most significant bit of the first byte.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::types::SnmpType;
+    /// let flags = SnmpType::SnmpOctetString(vec![0b1000_0100, 0b0000_0001]);
+    /// assert_eq!(flags.as_bits(), Some(vec![0, 5, 15]));
+    /// assert_eq!(SnmpType::SnmpOctetString(Vec::new()).as_bits(), Some(Vec::new()));
+    /// assert_eq!(SnmpType::SnmpInteger(1).as_bits(), None);
+    /// ```
+    pub fn as_bits(&self) -> Option<Vec<u32>> {
+        let bytes = self.as_bytes()?;
+        let bits = (0..bytes.len() as u32 * 8).filter(|bit| bytes[(bit / 8) as usize] & (0x80 >> (bit % 8)) != 0);
+        Some(bits.collect())
+    }
+
+    /// Returns true if this is SnmpNull.
+    pub fn is_null(&self) -> bool {
+        matches!(*self, SnmpType::SnmpNull)
+    }
+
+    /// Returns true if this is one of the exceptions a SNMPv2c agent answers with
+    /// instead of a value: NoSuchObject, NoSuchInstance or EndOfMibView.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::types::read_tlv;
+    /// let (value, _) = read_tlv(&[0x81, 0x00]).unwrap();
+    /// assert!(value.is_exception());
+    /// assert!(!read_tlv(&[0x05, 0x00]).unwrap().0.is_exception());
+    /// ```
+    pub fn is_exception(&self) -> bool {
+        matches!(*self, SnmpType::NoSuchObject | SnmpType::NoSuchInstance | SnmpType::EndOfMibView)
+    }
+
+    /// Returns the OID if this is a SnmpObjectID.
+    pub fn as_oid(&self) -> Option<&Oid> {
+        match *self {
+            SnmpType::SnmpObjectID(ref oid) => Some(oid),
+            _ => None,
+        }
+    }
+
+    /// Returns the address if this is a SnmpIpAddress.
+    pub fn as_ip(&self) -> Option<Ipv4Addr> {
+        match *self {
+            SnmpType::SnmpIpAddress(ip) => Some(ip),
+            _ => None,
+        }
+    }
+
+    /// Returns the count if this is a SnmpCounter32.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::types::SnmpType;
+    /// let value = SnmpType::SnmpCounter32(7);
+    /// assert_eq!(value.as_counter(), Some(7));
+    /// assert_eq!(value.as_gauge(), None);
+    /// ```
+    pub fn as_counter(&self) -> Option<u32> {
+        match *self {
+            SnmpType::SnmpCounter32(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if this is a SnmpGauge32.
+    pub fn as_gauge(&self) -> Option<u32> {
+        match *self {
+            SnmpType::SnmpGauge32(g) => Some(g),
+            _ => None,
+        }
+    }
+
+    /// Returns the hundredths of a second if this is a SnmpTimeTicks.
+    pub fn as_timeticks(&self) -> Option<u32> {
+        match *self {
+            SnmpType::SnmpTimeTicks(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if this is a SnmpOpaque wrapping an Opaque-Float or Opaque-Double,
+    /// as vendors use for readings such as temperatures and voltages.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::types::{read_tlv, SnmpType};
+    /// let (value, _) = read_tlv(&[0x44, 0x07, 0x9F, 0x78, 0x04, 0x41, 0xBC, 0x00, 0x00]).unwrap();
+    /// assert_eq!(value.as_f64(), Some(23.5));
+    /// assert_eq!(value.to_string(), "Opaque: Float: 23.5");
+    ///
+    /// let double = SnmpType::SnmpOpaque(vec![0x9F, 0x79, 0x08, 0xC0, 0x04, 0, 0, 0, 0, 0, 0]);
+    /// assert_eq!(double.as_f64(), Some(-2.5));
+    /// assert_eq!(SnmpType::SnmpOpaque(vec![0x04, 0x01, 0x00]).as_f64(), None);
+    /// ```
+    pub fn as_f64(&self) -> Option<f64> {
+        let bytes = match *self {
+            SnmpType::SnmpOpaque(ref bytes) => bytes,
+            _ => return None,
+        };
+        match bytes.len() {
+            7 if bytes[..3] == [0x9F, 0x78, 0x04] =>
+                Some(f32::from_bits(BigEndian::read_u32(&bytes[3..])) as f64),
+            11 if bytes[..3] == [0x9F, 0x79, 0x08] =>
+                Some(f64::from_bits(BigEndian::read_u64(&bytes[3..]))),
+            _ => None,
+        }
+    }
+
+    /// Returns the count if this is a SnmpCounter64.
+    pub fn as_counter64(&self) -> Option<u64> {
+        match *self {
+            SnmpType::SnmpCounter64(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Returns the values if this is a SnmpSequence.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::types::read_tlv;
+    /// // A variable binding: sysServices.0 = 72.
+    /// let data = [0x30, 0x0D, 0x06, 0x08, 0x2B, 0x06, 0x01, 0x02, 0x01, 0x01, 0x07, 0x00, 0x02, 0x01, 0x48];
+    /// let (value, consumed) = read_tlv(&data).unwrap();
+    /// assert_eq!(consumed, data.len());
+    /// let values = value.as_sequence().unwrap();
+    /// assert_eq!(values.len(), 2);
+    /// assert_eq!(values[0].as_oid().unwrap().arcs(), &[1, 3, 6, 1, 2, 1, 1, 7, 0]);
+    /// assert_eq!(values[1].as_integer(), Some(72));
+    /// assert_eq!(value.to_string(), "SEQUENCE: { OID: .1.3.6.1.2.1.1.7.0, INTEGER: 72 }");
+    ///
+    /// // An inner value running past the end of the sequence is an error.
+    /// let mut short = data;
+    /// short[1] = 0x0C;
+    /// assert!(read_tlv(&short[..14]).is_err());
+    /// ```
+    pub fn as_sequence(&self) -> Option<&[SnmpType]> {
+        match *self {
+            SnmpType::SnmpSequence(ref values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Encodes the value as BER, the way it is sent in a variable binding.
+    /// `read_tlv` decodes it back into the same value.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::types::SnmpType;
+    /// assert_eq!(SnmpType::SnmpInteger(-129).encode(), [0x02, 0x02, 0xFF, 0x7F]);
+    /// assert_eq!(SnmpType::SnmpGauge32(128).encode(), [0x42, 0x02, 0x00, 0x80]);
+    /// assert_eq!(SnmpType::SnmpNull.encode(), [0x05, 0x00]);
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        self.encode_snmp()
+    }
+}
+
+/// Formats the value the way net-snmp's `snmpget` prints it, with its type in front.
+///
+/// #Examples
+/// ```
+/// use std::net::Ipv4Addr;
+/// use rust_snmp::types::SnmpType;
+/// assert_eq!(SnmpType::SnmpTimeTicks(12345).to_string(), "Timeticks: (12345) 0:02:03.45");
+/// assert_eq!(SnmpType::SnmpTimeTicks(9000000).to_string(), "Timeticks: (9000000) 1 day, 1:00:00.00");
+/// assert_eq!(SnmpType::SnmpIpAddress(Ipv4Addr::new(10, 0, 0, 1)).to_string(), "IpAddress: 10.0.0.1");
+/// assert_eq!(SnmpType::SnmpString("foo".to_owned()).to_string(), "STRING: foo");
+/// assert_eq!(SnmpType::SnmpOctetString(vec![0x00, 0x1A, 0xFF]).to_string(), "Hex-STRING: 00 1a ff");
+/// assert_eq!(SnmpType::SnmpObjectID(vec![1, 3, 6, 1].into()).to_string(), "OID: .1.3.6.1");
+/// assert_eq!(SnmpType::SnmpInteger(-5).to_string(), "INTEGER: -5");
+/// ```
+impl fmt::Display for SnmpType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnmpType::SnmpInteger(i) => write!(f, "INTEGER: {}", i),
+            SnmpType::SnmpString(ref s) => write!(f, "STRING: {}", s),
+            SnmpType::SnmpOctetString(ref bytes) => write!(f, "Hex-STRING: {}", Hex(bytes)),
+            SnmpType::SnmpNull => write!(f, "NULL"),
+            SnmpType::SnmpObjectID(ref oid) => write!(f, "OID: .{}", oid),
+            SnmpType::SnmpIpAddress(ip) => write!(f, "IpAddress: {}", ip),
+            SnmpType::SnmpCounter32(c) => write!(f, "Counter32: {}", c),
+            SnmpType::SnmpGauge32(g) => write!(f, "Gauge32: {}", g),
+            SnmpType::SnmpTimeTicks(t) => {
+                let days = t / 8_640_000;
+                write!(f, "Timeticks: ({}) ", t)?;
+                match days {
+                    0 => (),
+                    1 => write!(f, "1 day, ")?,
+                    _ => write!(f, "{} days, ", days)?,
+                }
+                write!(f, "{}:{:02}:{:02}.{:02}", t / 360_000 % 24, t / 6000 % 60, t / 100 % 60, t % 100)
+            },
+            SnmpType::SnmpOpaque(ref bytes) => match self.as_f64() {
+                Some(number) => write!(f, "Opaque: Float: {}", number),
+                None => write!(f, "OPAQUE: {}", Hex(bytes)),
+            },
+            SnmpType::SnmpCounter64(c) => write!(f, "Counter64: {}", c),
+            SnmpType::NoSuchObject => write!(f, "No Such Object available on this agent at this OID"),
+            SnmpType::NoSuchInstance => write!(f, "No Such Instance currently exists at this OID"),
+            SnmpType::EndOfMibView => write!(f, "No more variables left in this MIB View (It is past the end of the MIB tree)"),
+            SnmpType::SnmpSequence(ref values) => {
+                write!(f, "SEQUENCE: {{")?;
+                for (i, value) in values.iter().enumerate() {
+                    write!(f, "{}{}", if i > 0 { ", " } else { " " }, value)?;
+                }
+                write!(f, " }}")
+            },
+        }
+    }
+}
+
+/// A 32-bit counter, encoded with the Counter32 application tag.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::types::{Counter32, SnmpType};
+/// let value: SnmpType = Counter32(1500).into();
+/// assert_eq!(value.as_counter(), Some(1500));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Counter32(pub u32);
+
+/// A 32-bit gauge, encoded with the Gauge32 application tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gauge32(pub u32);
+
+/// Time in hundredths of a second, encoded with the TimeTicks application tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeTicks(pub u32);
+
+impl From<Counter32> for SnmpType {
+    fn from(value: Counter32) -> Self {
+        SnmpType::SnmpCounter32(value.0)
+    }
+}
+
+impl From<Gauge32> for SnmpType {
+    fn from(value: Gauge32) -> Self {
+        SnmpType::SnmpGauge32(value.0)
+    }
+}
+
+impl From<TimeTicks> for SnmpType {
+    fn from(value: TimeTicks) -> Self {
+        SnmpType::SnmpTimeTicks(value.0)
+    }
+}
+
+/// The SNMP protocol versions that can be used for community-based requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnmpVersion {
+    /// SNMPv1.
+    V1 = 0,
+    /// SNMPv2c, which adds GetBulk and 64-bit counters.
+    V2c = 1,
+}
+
+/// The transports requests can be sent over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnmpTransport {
+    /// UDP, which every agent supports.
+    Udp,
+    /// TCP, as described in RFC 3430, which avoids dropped fragments for large responses.
+    /// Requests are not resent over TCP, as the stream is already reliable.
+    Tcp,
+}
+
+/// The types of PDU a message can carry, identified by the tag they are encoded with.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::types::PduType;
+/// assert_eq!(PduType::from(0xA2), PduType::Response);
+/// assert_eq!(u8::from(PduType::Report), 0xA8);
+/// assert_eq!(PduType::from(0xAF), PduType::Other(0xAF));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PduType {
+    /// A GetRequest, tagged 0xA0.
+    GetRequest,
+    /// A GetNextRequest, tagged 0xA1.
+    GetNextRequest,
+    /// A response, tagged 0xA2, which SNMPv1 calls a GetResponse.
+    Response,
+    /// A SetRequest, tagged 0xA3.
+    SetRequest,
+    /// An SNMPv1 trap, tagged 0xA4.
+    Trap,
+    /// A GetBulkRequest, tagged 0xA5.
+    GetBulkRequest,
+    /// An InformRequest, tagged 0xA6.
+    InformRequest,
+    /// An SNMPv2 trap, tagged 0xA7.
+    TrapV2,
+    /// A report, tagged 0xA8, which agents send to say why a request was not answered.
+    Report,
+    /// Any other tag.
+    Other(u8),
+}
+
+impl From<u8> for PduType {
+    fn from(tag: u8) -> Self {
+        match tag {
+            0xA0 => PduType::GetRequest,
+            0xA1 => PduType::GetNextRequest,
+            0xA2 => PduType::Response,
+            0xA3 => PduType::SetRequest,
+            0xA4 => PduType::Trap,
+            0xA5 => PduType::GetBulkRequest,
+            0xA6 => PduType::InformRequest,
+            0xA7 => PduType::TrapV2,
+            0xA8 => PduType::Report,
+            tag => PduType::Other(tag),
+        }
+    }
+}
+
+impl From<PduType> for u8 {
+    fn from(pdu_type: PduType) -> Self {
+        match pdu_type {
+            PduType::GetRequest => 0xA0,
+            PduType::GetNextRequest => 0xA1,
+            PduType::Response => 0xA2,
+            PduType::SetRequest => 0xA3,
+            PduType::Trap => 0xA4,
+            PduType::GetBulkRequest => 0xA5,
+            PduType::InformRequest => 0xA6,
+            PduType::TrapV2 => 0xA7,
+            PduType::Report => 0xA8,
+            PduType::Other(tag) => tag,
+        }
+    }
+}
+
+/// The generic trap types of an SNMPv1 trap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenericTrap {
+    /// The agent is reinitializing, and its configuration may have changed.
+    ColdStart = 0,
+    /// The agent is reinitializing without changing its configuration.
+    WarmStart = 1,
+    /// A communication link has gone down.
+    LinkDown = 2,
+    /// A communication link has come up.
+    LinkUp = 3,
+    /// A request was not properly authenticated.
+    AuthenticationFailure = 4,
+    /// An EGP neighbor was lost.
+    EgpNeighborLoss = 5,
+    /// An enterprise-specific event, identified by the specific trap number.
+    EnterpriseSpecific = 6,
+}
+
+/// The error statuses an agent can respond with.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::types::SnmpResponseError;
+/// assert_eq!(SnmpResponseError::from(4), SnmpResponseError::ReadOnly);
+/// assert_eq!(SnmpResponseError::ReadOnly.to_string(), "the object is read-only");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnmpResponseError {
+    /// The response would not fit in a single message.
+    TooBig,
+    /// The OID does not exist on the agent.
+    NoSuchName,
+    /// The value in a SET request was not valid for the OID.
+    BadValue,
+    /// The OID can not be written to.
+    ReadOnly,
+    /// Some other error occured on the agent.
+    GenErr,
+    /// The OID is not accessible.
+    NoAccess,
+    /// The value in a SET request had the wrong type for the OID.
+    WrongType,
+    /// The value in a SET request had the wrong length for the OID.
+    WrongLength,
+    /// The value in a SET request was incorrectly encoded.
+    WrongEncoding,
+    /// The value in a SET request can never be assigned to the OID.
+    WrongValue,
+    /// The OID does not exist and can not be created.
+    NoCreation,
+    /// The value in a SET request can not be assigned to the OID right now.
+    InconsistentValue,
+    /// The agent lacks the resources to assign the value.
+    ResourceUnavailable,
+    /// The agent failed to commit the SET request.
+    CommitFailed,
+    /// The agent failed to undo a partially applied SET request.
+    UndoFailed,
+    /// The request was not authorized.
+    AuthorizationError,
+    /// The OID can not be written to or created.
+    NotWritable,
+    /// The OID does not exist and can not be created right now.
+    InconsistentName,
+    /// An error status not defined by the standard.
+    Other(i64),
+}
+
+impl From<i64> for SnmpResponseError {
+    fn from(status: i64) -> Self {
+        match status {
+            1 => SnmpResponseError::TooBig,
+            2 => SnmpResponseError::NoSuchName,
+            3 => SnmpResponseError::BadValue,
+            4 => SnmpResponseError::ReadOnly,
+            5 => SnmpResponseError::GenErr,
+            6 => SnmpResponseError::NoAccess,
+            7 => SnmpResponseError::WrongType,
+            8 => SnmpResponseError::WrongLength,
+            9 => SnmpResponseError::WrongEncoding,
+            10 => SnmpResponseError::WrongValue,
+            11 => SnmpResponseError::NoCreation,
+            12 => SnmpResponseError::InconsistentValue,
+            13 => SnmpResponseError::ResourceUnavailable,
+            14 => SnmpResponseError::CommitFailed,
+            15 => SnmpResponseError::UndoFailed,
+            16 => SnmpResponseError::AuthorizationError,
+            17 => SnmpResponseError::NotWritable,
+            18 => SnmpResponseError::InconsistentName,
+            n => SnmpResponseError::Other(n),
+        }
+    }
+}
+
+impl fmt::Display for SnmpResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnmpResponseError::TooBig => write!(f, "the response is too big to fit in one message"),
+            SnmpResponseError::NoSuchName => write!(f, "no such name"),
+            SnmpResponseError::BadValue => write!(f, "bad value"),
+            SnmpResponseError::ReadOnly => write!(f, "the object is read-only"),
+            SnmpResponseError::GenErr => write!(f, "general error"),
+            SnmpResponseError::NoAccess => write!(f, "no access"),
+            SnmpResponseError::WrongType => write!(f, "wrong type"),
+            SnmpResponseError::WrongLength => write!(f, "wrong length"),
+            SnmpResponseError::WrongEncoding => write!(f, "wrong encoding"),
+            SnmpResponseError::WrongValue => write!(f, "wrong value"),
+            SnmpResponseError::NoCreation => write!(f, "the object can not be created"),
+            SnmpResponseError::InconsistentValue => write!(f, "inconsistent value"),
+            SnmpResponseError::ResourceUnavailable => write!(f, "resource unavailable"),
+            SnmpResponseError::CommitFailed => write!(f, "commit failed"),
+            SnmpResponseError::UndoFailed => write!(f, "undo failed"),
+            SnmpResponseError::AuthorizationError => write!(f, "authorization error"),
+            SnmpResponseError::NotWritable => write!(f, "the object is not writable"),
+            SnmpResponseError::InconsistentName => write!(f, "inconsistent name"),
+            SnmpResponseError::Other(n) => write!(f, "unknown error status {}", n),
+        }
+    }
+}
+
+/// The problems an SNMPv3 agent can report, identified by the usmStats counter in a Report PDU.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::types::{Oid, SnmpV3Error};
+/// let oid = Oid::from(vec![1, 3, 6, 1, 6, 3, 15, 1, 1, 5, 0]);
+/// assert_eq!(SnmpV3Error::from(oid), SnmpV3Error::WrongDigest);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SnmpV3Error {
+    /// The agent does not support the requested security level for the user.
+    UnsupportedSecurityLevel,
+    /// The engine boots or time in the request was outside the time window of the agent.
+    NotInTimeWindow,
+    /// The user is not known to the agent.
+    UnknownUserName,
+    /// The engine ID in the request is not known to the agent.
+    UnknownEngineId,
+    /// The request had the wrong digest, usually because of a wrong authentication password.
+    WrongDigest,
+    /// The request could not be decrypted, usually because of a wrong privacy password.
+    DecryptionError,
+    /// A report not defined by the user-based security model, carrying the OID of its counter.
+    Other(Oid),
+}
+
+impl From<Oid> for SnmpV3Error {
+    fn from(oid: Oid) -> Self {
+        // usmStats counters live under 1.3.6.1.6.3.15.1.1.
+        match oid.arcs() {
+            [1, 3, 6, 1, 6, 3, 15, 1, 1, 1, 0] => SnmpV3Error::UnsupportedSecurityLevel,
+            [1, 3, 6, 1, 6, 3, 15, 1, 1, 2, 0] => SnmpV3Error::NotInTimeWindow,
+            [1, 3, 6, 1, 6, 3, 15, 1, 1, 3, 0] => SnmpV3Error::UnknownUserName,
+            [1, 3, 6, 1, 6, 3, 15, 1, 1, 4, 0] => SnmpV3Error::UnknownEngineId,
+            [1, 3, 6, 1, 6, 3, 15, 1, 1, 5, 0] => SnmpV3Error::WrongDigest,
+            [1, 3, 6, 1, 6, 3, 15, 1, 1, 6, 0] => SnmpV3Error::DecryptionError,
+            _ => SnmpV3Error::Other(oid),
+        }
+    }
+}
+
+impl fmt::Display for SnmpV3Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnmpV3Error::UnsupportedSecurityLevel => write!(f, "unsupported security level"),
+            SnmpV3Error::NotInTimeWindow => write!(f, "the request is not in the time window"),
+            SnmpV3Error::UnknownUserName => write!(f, "unknown user name"),
+            SnmpV3Error::UnknownEngineId => write!(f, "unknown engine ID"),
+            SnmpV3Error::WrongDigest => write!(f, "wrong digest, check the authentication password"),
+            SnmpV3Error::DecryptionError => write!(f, "decryption error, check the privacy password"),
+            SnmpV3Error::Other(ref oid) => write!(f, "unknown report {}", oid),
+        }
+    }
 }
 
 /// Various errors that can occur.
+///
+/// #Examples
+/// Whether a refused request surfaces as `Unreachable` depends on the host sending back
+/// an ICMP port-unreachable, so this example is not run.
+/// ```no_run
+/// use std::net::UdpSocket;
+/// use rust_snmp::snmpv1::Request;
+/// use rust_snmp::types::SnmpError;
+///
+/// // Find a port nothing listens on, so the host refuses the request.
+/// let address = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+/// let request = Request::new(address, "public", vec![1, 3, 6, 1, 2, 1, 1, 5, 0]);
+/// match request.send() {
+///     Err(SnmpError::Unreachable) => (),
+///     other => panic!("expected the host to be unreachable, got {:?}", other),
+/// }
+/// ```
 #[derive(Debug)]
 pub enum SnmpError {
-    /// The packet is too short to parse.
-    PacketTooShort,
+    /// A value needs more bytes than are left: its contents start at `offset` and are `expected`
+    /// bytes long, but only `actual` bytes follow, as when a datagram was cut short.
+    /// The offset always counts from the start of the bytes given to the function that failed,
+    /// which is the whole packet for the `decode` functions and for replies to requests.
+    PacketTooShort {
+        /// Where the contents of the value start.
+        offset: usize,
+        /// How many bytes the value needs.
+        expected: usize,
+        /// How many bytes are left.
+        actual: usize,
+    },
     /// The type specified in the packet is invalid.
     InvalidType,
     /// The packet could not be parsed in the wanted manner.
     ParsingError,
-    /// The SNMP response contained an error
-    ResponseError(i64),
+    /// The SNMP response contained an error status, and the index of the variable binding
+    /// that caused it, starting at 1.
+    ResponseError(SnmpResponseError, i64),
+    /// The SNMPv3 agent responded with a Report PDU describing why the request failed.
+    ReportError(SnmpV3Error),
+    /// An OID could not be parsed from a string, or its first two arcs can not be encoded.
+    InvalidOid,
+    /// The response did not carry the request ID that was sent. Requests throw such replies away
+    /// and go on waiting for their own, timing out if it never comes.
+    RequestIdMismatch,
+    /// The response carried a different community than the request.
+    CommunityMismatch,
+    /// No response arrived before the request timed out, so the host may be up but
+    /// not answering SNMP.
+    Timeout,
+    /// The host could not be reached, or refused the request because nothing listens on the port.
+    /// This is only known when the host or a router answers with an ICMP error.
+    Unreachable,
+    /// The packet does not fit in the buffer it was being written to.
+    BufferTooSmall,
+    /// The response is larger than the largest message the request accepts.
+    ResponseTooLarge,
+    /// The agent answered a walk with an OID that does not come after the one asked for,
+    /// which would make the walk go around in circles. Holds what was found before it.
+    OidNotIncreasing(Vec<(Oid, SnmpType)>),
+    /// A walk found more variables than the request allows. Holds those that were found.
+    WalkLimitExceeded(Vec<(Oid, SnmpType)>),
+    /// The packet holds more variable bindings, or nests sequences deeper, than `DecodeLimits` allows.
+    LimitExceeded,
+    /// The request is missing something it needs, or asks for settings that do not go together,
+    /// so it was never sent. Holds what is wrong with it.
+    InvalidRequest(&'static str),
+    /// The request asks for something this library does not support, such as sending over TCP
+    /// without blocking. Holds what is not supported.
+    Unsupported(&'static str),
     /// An IO error occured when sending or receiving the packets.
     Io(io::Error),
     /// An UTF8 parsing error occured when parsing a string.
     Utf8(string::FromUtf8Error),
 }
 
+/// What kind of `SnmpError` occured, without the details that can not be compared,
+/// for counting and grouping errors such as across many polled devices.
+///
+/// #Examples
+/// ```
+/// use std::collections::HashMap;
+/// use std::io;
+/// use rust_snmp::types::{SnmpError, SnmpErrorKind};
+///
+/// let errors = vec![SnmpError::Timeout,
+///                   SnmpError::from(io::Error::new(io::ErrorKind::PermissionDenied, "denied")),
+///                   SnmpError::Timeout];
+/// let mut tally = HashMap::new();
+/// for error in &errors {
+///     *tally.entry(error.kind()).or_insert(0) += 1;
+/// }
+/// assert_eq!(tally[&SnmpErrorKind::Timeout], 2);
+/// assert_eq!(tally[&SnmpErrorKind::Io(io::ErrorKind::PermissionDenied)], 1);
+///
+/// // Errors can be kept around too, with IO errors copied as their kind and message.
+/// let kept = errors[1].clone();
+/// assert_eq!(kept.kind(), errors[1].kind());
+/// assert_eq!(kept.to_string(), "IO error: denied");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SnmpErrorKind {
+    /// See `SnmpError::PacketTooShort`.
+    PacketTooShort,
+    /// See `SnmpError::InvalidType`.
+    InvalidType,
+    /// See `SnmpError::ParsingError`.
+    ParsingError,
+    /// See `SnmpError::ResponseError`, keeping the error status.
+    ResponseError(SnmpResponseError),
+    /// See `SnmpError::ReportError`.
+    ReportError(SnmpV3Error),
+    /// See `SnmpError::InvalidOid`.
+    InvalidOid,
+    /// See `SnmpError::RequestIdMismatch`.
+    RequestIdMismatch,
+    /// See `SnmpError::CommunityMismatch`.
+    CommunityMismatch,
+    /// See `SnmpError::Timeout`.
+    Timeout,
+    /// See `SnmpError::Unreachable`.
+    Unreachable,
+    /// See `SnmpError::BufferTooSmall`.
+    BufferTooSmall,
+    /// See `SnmpError::ResponseTooLarge`.
+    ResponseTooLarge,
+    /// See `SnmpError::OidNotIncreasing`.
+    OidNotIncreasing,
+    /// See `SnmpError::WalkLimitExceeded`.
+    WalkLimitExceeded,
+    /// See `SnmpError::LimitExceeded`.
+    LimitExceeded,
+    /// See `SnmpError::InvalidRequest`.
+    InvalidRequest,
+    /// See `SnmpError::Unsupported`.
+    Unsupported,
+    /// See `SnmpError::Io`, keeping the kind of IO error.
+    Io(io::ErrorKind),
+    /// See `SnmpError::Utf8`.
+    Utf8,
+}
+
+impl SnmpError {
+    /// Returns what kind of error this is.
+    pub fn kind(&self) -> SnmpErrorKind {
+        match *self {
+            SnmpError::PacketTooShort { .. } => SnmpErrorKind::PacketTooShort,
+            SnmpError::InvalidType => SnmpErrorKind::InvalidType,
+            SnmpError::ParsingError => SnmpErrorKind::ParsingError,
+            SnmpError::ResponseError(status, _) => SnmpErrorKind::ResponseError(status),
+            SnmpError::ReportError(ref report) => SnmpErrorKind::ReportError(report.clone()),
+            SnmpError::InvalidOid => SnmpErrorKind::InvalidOid,
+            SnmpError::RequestIdMismatch => SnmpErrorKind::RequestIdMismatch,
+            SnmpError::CommunityMismatch => SnmpErrorKind::CommunityMismatch,
+            SnmpError::Timeout => SnmpErrorKind::Timeout,
+            SnmpError::Unreachable => SnmpErrorKind::Unreachable,
+            SnmpError::BufferTooSmall => SnmpErrorKind::BufferTooSmall,
+            SnmpError::ResponseTooLarge => SnmpErrorKind::ResponseTooLarge,
+            SnmpError::OidNotIncreasing(_) => SnmpErrorKind::OidNotIncreasing,
+            SnmpError::WalkLimitExceeded(_) => SnmpErrorKind::WalkLimitExceeded,
+            SnmpError::LimitExceeded => SnmpErrorKind::LimitExceeded,
+            SnmpError::InvalidRequest(_) => SnmpErrorKind::InvalidRequest,
+            SnmpError::Unsupported(_) => SnmpErrorKind::Unsupported,
+            SnmpError::Io(ref e) => SnmpErrorKind::Io(e.kind()),
+            SnmpError::Utf8(_) => SnmpErrorKind::Utf8,
+        }
+    }
+
+    /// Returns the position of the variable binding an agent blamed for an error response,
+    /// counting from zero. A SNMPv1 agent fails the whole request when one OID is missing,
+    /// so this tells which OID to leave out when asking for the rest again.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::snmpv1::Request;
+    /// use rust_snmp::types::{SnmpErrorKind, SnmpResponseError};
+    ///
+    /// // The agent answers noSuchName for the second variable binding the first time it is asked.
+    /// # let mut attempt = 0;
+    /// # let agent_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    /// # let address = agent_socket.local_addr().unwrap().to_string();
+    /// # let mut answer = move |mut message: rust_snmp::snmpv1::CommunityMessage| {
+    /// #     message.pdu.pdu_type = rust_snmp::types::PduType::Response;
+    /// #     if attempt == 0 {
+    /// #         message.pdu.error_status = 2;
+    /// #         message.pdu.error_index = 2;
+    /// #     }
+    /// #     attempt += 1;
+    /// #     message
+    /// # };
+    /// # let _agent = std::thread::spawn(move || {
+    /// #     let mut buf = [0; 65535];
+    /// #     let mut requests = Vec::new();
+    /// #     for _ in 0..2 {
+    /// #         let (length, from) = agent_socket.recv_from(&mut buf).unwrap();
+    /// #         let message = rust_snmp::snmpv1::CommunityMessage::decode(&buf[..length]).unwrap();
+    /// #         agent_socket.send_to(&answer(message.clone()).encode(), from).unwrap();
+    /// #         requests.push(message);
+    /// #     }
+    /// #     requests
+    /// # });
+    ///
+    /// let mut request = Request::from_oids(address, "public", vec![
+    ///     vec![1, 3, 6, 1, 2, 1, 1, 1, 0].into(),
+    ///     vec![1, 3, 6, 1, 2, 1, 1, 99, 0].into(),
+    ///     vec![1, 3, 6, 1, 2, 1, 1, 5, 0].into(),
+    /// ]);
+    /// let error = request.send().unwrap_err();
+    /// assert_eq!(error.kind(), SnmpErrorKind::ResponseError(SnmpResponseError::NoSuchName));
+    /// assert_eq!(error.failed_varbind(), Some(1));
+    ///
+    /// request.oids.remove(1);
+    /// assert_eq!(request.send().unwrap().varbinds().len(), 2);
+    /// ```
+    pub fn failed_varbind(&self) -> Option<usize> {
+        match *self {
+            SnmpError::ResponseError(_, index) if index > 0 => Some(index as usize - 1),
+            _ => None,
+        }
+    }
+}
+
+/// IO errors can not be cloned, so they are copied as a new error with the same kind and message.
+impl Clone for SnmpError {
+    fn clone(&self) -> Self {
+        match *self {
+            SnmpError::PacketTooShort { offset, expected, actual } =>
+                SnmpError::PacketTooShort { offset: offset, expected: expected, actual: actual },
+            SnmpError::InvalidType => SnmpError::InvalidType,
+            SnmpError::ParsingError => SnmpError::ParsingError,
+            SnmpError::ResponseError(status, index) => SnmpError::ResponseError(status, index),
+            SnmpError::ReportError(ref report) => SnmpError::ReportError(report.clone()),
+            SnmpError::InvalidOid => SnmpError::InvalidOid,
+            SnmpError::RequestIdMismatch => SnmpError::RequestIdMismatch,
+            SnmpError::CommunityMismatch => SnmpError::CommunityMismatch,
+            SnmpError::Timeout => SnmpError::Timeout,
+            SnmpError::Unreachable => SnmpError::Unreachable,
+            SnmpError::BufferTooSmall => SnmpError::BufferTooSmall,
+            SnmpError::ResponseTooLarge => SnmpError::ResponseTooLarge,
+            SnmpError::OidNotIncreasing(ref found) => SnmpError::OidNotIncreasing(found.clone()),
+            SnmpError::WalkLimitExceeded(ref found) => SnmpError::WalkLimitExceeded(found.clone()),
+            SnmpError::LimitExceeded => SnmpError::LimitExceeded,
+            SnmpError::InvalidRequest(reason) => SnmpError::InvalidRequest(reason),
+            SnmpError::Unsupported(reason) => SnmpError::Unsupported(reason),
+            SnmpError::Io(ref e) => SnmpError::Io(io::Error::new(e.kind(), e.to_string())),
+            SnmpError::Utf8(ref e) => SnmpError::Utf8(e.clone()),
+        }
+    }
+}
+
+/// An object identifier, stored as its numeric arcs.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::types::{read_tlv, Oid, SnmpType};
+/// let oid: Oid = "1.3.6.1.2.1.1.5.0".parse().unwrap();
+/// assert_eq!(oid.arcs(), &[1, 3, 6, 1, 2, 1, 1, 5, 0]);
+///
+/// let oid: Oid = ".1.3.6.1.4.1.2021.4294967295".parse().unwrap();
+/// assert_eq!(oid.arcs()[7], 4294967295);
+///
+/// assert!("1.3.six".parse::<Oid>().is_err());
+/// assert!("4.1".parse::<Oid>().is_err());
+///
+/// // Arcs are sent in base-128, with the high bit set on every byte of an arc but the last.
+/// let oid = Oid::from(vec![1, 3, 6, 1, 4, 1, 2680, 131072, 1]);
+/// let encoded = SnmpType::SnmpObjectID(oid.clone()).encode();
+/// assert_eq!(encoded, [0x06, 0x0B, 0x2B, 0x06, 0x01, 0x04, 0x01, 0x94, 0x78, 0x88, 0x80, 0x00, 0x01]);
+/// match read_tlv(&encoded).unwrap() {
+///     (SnmpType::SnmpObjectID(decoded), 13) => assert_eq!(decoded, oid),
+///     other => panic!("expected the OID back, got {:?}", other),
+/// }
+///
+/// // Common standard OIDs can be named instead.
+/// let oid: Oid = "sysName.0".parse().unwrap();
+/// assert_eq!(oid.arcs(), &[1, 3, 6, 1, 2, 1, 1, 5, 0]);
+/// let oid: Oid = "ifDescr.3".parse().unwrap();
+/// assert_eq!(oid.arcs(), &[1, 3, 6, 1, 2, 1, 2, 2, 1, 2, 3]);
+/// assert!("sysNonsense.0".parse::<Oid>().is_err());
+///
+/// // OIDs print in dotted-decimal and sort in the order agents walk them.
+/// let mut oids: Vec<Oid> = vec!["1.3.6.1.2.1.2".parse().unwrap(),
+///                               "1.3.6.1.2.1.10".parse().unwrap(),
+///                               "1.3.6.1.2.1.1.5.0".parse().unwrap(),
+///                               "1.3.6.1.2.1.1".parse().unwrap()];
+/// oids.sort();
+/// let printed: Vec<String> = oids.iter().map(|oid| oid.to_string()).collect();
+/// assert_eq!(printed, ["1.3.6.1.2.1.1", "1.3.6.1.2.1.1.5.0", "1.3.6.1.2.1.2", "1.3.6.1.2.1.10"]);
+/// assert!(oids[0].is_prefix_of(&oids[1]));
+/// assert!(!oids[1].is_prefix_of(&oids[2]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Oid {
+    arcs: Vec<u32>,
+}
+
+impl Oid {
+    /// Returns the numeric arcs of the OID.
+    pub fn arcs(&self) -> &[u32] {
+        &self.arcs
+    }
+
+    /// Checks that the OID can be encoded: it needs at least two arcs, the first of which is 0, 1 or 2,
+    /// and below 0 and 1 the second arc must be less than 40.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::types::Oid;
+    /// assert!(Oid::from(vec![0, 39]).validate().is_ok());
+    /// assert!(Oid::from(vec![1, 3, 6, 1]).validate().is_ok());
+    /// assert!(Oid::from(vec![2, 999, 3]).validate().is_ok());
+    ///
+    /// assert!(Oid::from(vec![0, 40]).validate().is_err());
+    /// assert!(Oid::from(vec![3, 1]).validate().is_err());
+    /// assert!(Oid::from(vec![1]).validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), SnmpError> {
+        match (self.arcs.first(), self.arcs.get(1)) {
+            (Some(&2), Some(&second)) if second <= u32::MAX - 80 => Ok(()),
+            (Some(&first), Some(&second)) if first < 2 && second < 40 => Ok(()),
+            _ => Err(SnmpError::InvalidOid),
+        }
+    }
+
+    /// Returns true if `other` lies in the subtree rooted at this OID.
+    pub fn is_prefix_of(&self, other: &Oid) -> bool {
+        other.arcs.starts_with(&self.arcs)
+    }
+
+    /// Returns the OID that comes right after this one, which is this one with `.0` appended,
+    /// as no OID sorts between the two.
+    ///
+    /// #Examples
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use rust_snmp::types::Oid;
+    ///
+    /// // An agent answers GETNEXT with the first OID it knows after the one asked for.
+    /// let mut mib = BTreeMap::new();
+    /// mib.insert("1.3.6.1.2.1.1.1.0".parse::<Oid>().unwrap(), "Linux");
+    /// mib.insert("1.3.6.1.2.1.1.5.0".parse::<Oid>().unwrap(), "router");
+    /// let get_next = |oid: &Oid| mib.range(oid.successor()..).next();
+    ///
+    /// let sys_descr: Oid = "1.3.6.1.2.1.1.1.0".parse().unwrap();
+    /// assert_eq!(get_next(&"1.3.6.1.2.1.1".parse().unwrap()).unwrap().0, &sys_descr);
+    /// assert_eq!(get_next(&sys_descr).unwrap().1, &"router");
+    /// assert_eq!(sys_descr.successor().to_string(), "1.3.6.1.2.1.1.1.0.0");
+    /// ```
+    pub fn successor(&self) -> Oid {
+        let mut arcs = self.arcs.clone();
+        arcs.push(0);
+        Oid { arcs: arcs }
+    }
+
+    /// Returns the first OID after this one and its whole subtree, which a walk of this OID
+    /// never reaches, or `None` if every arc is already as large as it can be.
+    /// An OID lies in the subtree exactly when it is at least this one but less than the sibling.
+    ///
+    /// #Examples
+    /// ```
+    /// use rust_snmp::types::Oid;
+    /// let if_table: Oid = "1.3.6.1.2.1.2.2".parse().unwrap();
+    /// let end = if_table.next_sibling().unwrap();
+    /// assert_eq!(end.to_string(), "1.3.6.1.2.1.2.3");
+    ///
+    /// let if_descr: Oid = "1.3.6.1.2.1.2.2.1.2.1".parse().unwrap();
+    /// assert!(if_table <= if_descr && if_descr < end);
+    ///
+    /// // The last arc can not be incremented, so the one before it is.
+    /// let oid = Oid::from(vec![1, 3, 6, 4294967295]);
+    /// assert_eq!(oid.next_sibling().unwrap().to_string(), "1.3.7");
+    /// assert_eq!(Oid::from(vec![4294967295]).next_sibling(), None);
+    /// ```
+    pub fn next_sibling(&self) -> Option<Oid> {
+        let mut arcs = self.arcs.clone();
+        while let Some(arc) = arcs.pop() {
+            if let Some(next) = arc.checked_add(1) {
+                arcs.push(next);
+                return Some(Oid { arcs: arcs });
+            }
+        }
+        None
+    }
+}
+
+/// Names of commonly used standard OIDs, which `Oid::from_str` accepts in place of their numeric arcs.
+const KNOWN_OIDS: &[(&str, &[u32])] = &[
+    ("internet",        &[1, 3, 6, 1]),
+    ("mib-2",           &[1, 3, 6, 1, 2, 1]),
+    ("enterprises",     &[1, 3, 6, 1, 4, 1]),
+    ("system",          &[1, 3, 6, 1, 2, 1, 1]),
+    ("sysDescr",        &[1, 3, 6, 1, 2, 1, 1, 1]),
+    ("sysObjectID",     &[1, 3, 6, 1, 2, 1, 1, 2]),
+    ("sysUpTime",       &[1, 3, 6, 1, 2, 1, 1, 3]),
+    ("sysContact",      &[1, 3, 6, 1, 2, 1, 1, 4]),
+    ("sysName",         &[1, 3, 6, 1, 2, 1, 1, 5]),
+    ("sysLocation",     &[1, 3, 6, 1, 2, 1, 1, 6]),
+    ("sysServices",     &[1, 3, 6, 1, 2, 1, 1, 7]),
+    ("interfaces",      &[1, 3, 6, 1, 2, 1, 2]),
+    ("ifNumber",        &[1, 3, 6, 1, 2, 1, 2, 1]),
+    ("ifTable",         &[1, 3, 6, 1, 2, 1, 2, 2]),
+    ("ifEntry",         &[1, 3, 6, 1, 2, 1, 2, 2, 1]),
+    ("ifIndex",         &[1, 3, 6, 1, 2, 1, 2, 2, 1, 1]),
+    ("ifDescr",         &[1, 3, 6, 1, 2, 1, 2, 2, 1, 2]),
+    ("ifType",          &[1, 3, 6, 1, 2, 1, 2, 2, 1, 3]),
+    ("ifMtu",           &[1, 3, 6, 1, 2, 1, 2, 2, 1, 4]),
+    ("ifSpeed",         &[1, 3, 6, 1, 2, 1, 2, 2, 1, 5]),
+    ("ifPhysAddress",   &[1, 3, 6, 1, 2, 1, 2, 2, 1, 6]),
+    ("ifAdminStatus",   &[1, 3, 6, 1, 2, 1, 2, 2, 1, 7]),
+    ("ifOperStatus",    &[1, 3, 6, 1, 2, 1, 2, 2, 1, 8]),
+    ("ifLastChange",    &[1, 3, 6, 1, 2, 1, 2, 2, 1, 9]),
+    ("ifInOctets",      &[1, 3, 6, 1, 2, 1, 2, 2, 1, 10]),
+    ("ifInUcastPkts",   &[1, 3, 6, 1, 2, 1, 2, 2, 1, 11]),
+    ("ifInDiscards",    &[1, 3, 6, 1, 2, 1, 2, 2, 1, 13]),
+    ("ifInErrors",      &[1, 3, 6, 1, 2, 1, 2, 2, 1, 14]),
+    ("ifOutOctets",     &[1, 3, 6, 1, 2, 1, 2, 2, 1, 16]),
+    ("ifOutUcastPkts",  &[1, 3, 6, 1, 2, 1, 2, 2, 1, 17]),
+    ("ifOutDiscards",   &[1, 3, 6, 1, 2, 1, 2, 2, 1, 19]),
+    ("ifOutErrors",     &[1, 3, 6, 1, 2, 1, 2, 2, 1, 20]),
+    ("ifXTable",        &[1, 3, 6, 1, 2, 1, 31, 1, 1]),
+    ("ifName",          &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1, 1]),
+    ("ifHCInOctets",    &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1, 6]),
+    ("ifHCOutOctets",   &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1, 10]),
+    ("ifHighSpeed",     &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1, 15]),
+    ("ifAlias",         &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1, 18]),
+    ("snmpTrapOID",     &[1, 3, 6, 1, 6, 3, 1, 1, 4, 1]),
+];
+
+/// Formats bytes as lowercase, space-separated hex, without allocating unless it is.
+/// Every hex rendering of bytes goes through it, so the same bytes always print the same way.
+pub(crate) struct Hex<'a>(pub(crate) &'a [u8]);
+
+impl<'a> fmt::Display for Hex<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Oid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, arc) in self.arcs.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", arc)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Oid {
+    type Err = SnmpError;
+
+    /// Parses a dotted-decimal OID such as "1.3.6.1.2.1.1.5.0". A leading dot is allowed.
+    /// The first arcs may also be given as the name of a common standard OID, as in "sysName.0",
+    /// or with the `mib` feature, as any name known to the installed `mib::Mib`.
+    fn from_str(s: &str) -> Result<Self, SnmpError> {
+        let s = s.strip_prefix('.').unwrap_or(s);
+        let mut parts = s.split('.');
+        let mut arcs = Vec::new();
+        if let Some(first) = parts.next() {
+            match first.parse::<u32>() {
+                Ok(arc) => arcs.push(arc),
+                Err(_) => match KNOWN_OIDS.iter().find(|&&(name, _)| name == first) {
+                    Some(&(_, known)) => arcs.extend_from_slice(known),
+                    None => arcs.extend_from_slice(resolve_name(first)?.arcs()),
+                },
+            }
+        }
+        for arc in parts {
+            arcs.push(arc.parse::<u32>().map_err(|_| SnmpError::InvalidOid)?);
+        }
+        let oid = Oid { arcs: arcs };
+        oid.validate()?;
+        Ok(oid)
+    }
+}
+
+/// Resolves a name the built-in table does not know with the installed MIB resolver.
+#[cfg(feature = "mib")]
+fn resolve_name(name: &str) -> Result<Oid, SnmpError> {
+    ::mib::resolve_installed(name).ok_or(SnmpError::InvalidOid)
+}
+
+#[cfg(not(feature = "mib"))]
+fn resolve_name(_name: &str) -> Result<Oid, SnmpError> {
+    Err(SnmpError::InvalidOid)
+}
+
+impl From<Vec<u32>> for Oid {
+    fn from(arcs: Vec<u32>) -> Self {
+        Oid { arcs: arcs }
+    }
+}
+
+impl<'a> From<&'a [u32]> for Oid {
+    fn from(arcs: &'a [u32]) -> Self {
+        Oid { arcs: arcs.to_vec() }
+    }
+}
+
+impl fmt::Display for SnmpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnmpError::PacketTooShort { offset, expected, actual } =>
+                write!(f, "the packet is too short to parse: the value at byte {} needs {} bytes, but only {} follow",
+                       offset, expected, actual),
+            SnmpError::InvalidType => write!(f, "the value has an invalid or unexpected type"),
+            SnmpError::ParsingError => write!(f, "the packet could not be parsed"),
+            SnmpError::ResponseError(ref status, index) =>
+                write!(f, "the agent responded with an error: {} (variable binding {})", status, index),
+            SnmpError::ReportError(ref report) => write!(f, "the agent reported an error: {}", report),
+            SnmpError::InvalidOid => write!(f, "the OID is not valid"),
+            SnmpError::RequestIdMismatch => write!(f, "the response does not match the request ID"),
+            SnmpError::CommunityMismatch => write!(f, "the response does not match the community"),
+            SnmpError::Timeout => write!(f, "timed out waiting for a response"),
+            SnmpError::Unreachable => write!(f, "the host is unreachable"),
+            SnmpError::BufferTooSmall => write!(f, "the buffer is too small to hold the packet"),
+            SnmpError::ResponseTooLarge => write!(f, "the response is larger than the receive buffer"),
+            SnmpError::OidNotIncreasing(ref found) =>
+                write!(f, "the agent answered with an OID that does not increase, after {} variables", found.len()),
+            SnmpError::WalkLimitExceeded(ref found) =>
+                write!(f, "the walk was stopped after {} variables", found.len()),
+            SnmpError::LimitExceeded => write!(f, "the packet holds more than the decoding limits allow"),
+            SnmpError::InvalidRequest(reason) => write!(f, "the request is not valid: {}", reason),
+            SnmpError::Unsupported(reason) => write!(f, "not supported: {}", reason),
+            SnmpError::Io(ref e) => write!(f, "IO error: {}", e),
+            SnmpError::Utf8(ref e) => write!(f, "the string is not valid UTF-8: {}", e),
+        }
+    }
+}
+
+impl error::Error for SnmpError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            SnmpError::Io(ref e) => Some(e),
+            SnmpError::Utf8(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl From<io::Error> for SnmpError {
     fn from(error: io::Error) -> Self {
         SnmpError::Io(error)
@@ -51,27 +1158,318 @@ impl From<string::FromUtf8Error> for SnmpError {
     }
 }
 
-pub(crate) fn extract_value(mut data: &mut slice::Iter<u8>) -> Result<SnmpType, SnmpError> {
-    let datatype = *data.next().ok_or(SnmpError::PacketTooShort)?;
-    let length   = *data.next().ok_or(SnmpError::PacketTooShort)? as usize;
+/// Reads a BER length, in either the short or the long form.
+/// Use this to step into a sequence after reading its tag.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::types::read_length;
+/// let data = [0x82, 0x01, 0x00, 0x30];
+/// let mut iterator = data.iter();
+/// assert_eq!(read_length(&mut iterator).unwrap(), 256);
+/// assert_eq!(iterator.as_slice(), &[0x30]);
+/// ```
+pub fn read_length(data: &mut slice::Iter<u8>) -> Result<usize, SnmpError> {
+    let first = next_byte(data)?;
 
-    println!();
-    println!("Type:   {:00X}", datatype);
-    println!("Length: {:00}", length);
+    // In the short form the byte is the length itself.
+    if first & 0x80 == 0 {
+        return Ok(first as usize);
+    }
+
+    // In the long form the low bits count the bytes holding the length.
+    // A count of zero is the indefinite form, which SNMP does not allow.
+    let count = (first & 0x7F) as usize;
+    if count == 0 || count > 4 {
+        return Err(SnmpError::ParsingError);
+    }
 
+    if data.len() < count {
+        return Err(SnmpError::PacketTooShort { offset: 1, expected: count, actual: data.len() });
+    }
+    let mut length = 0;
+    for byte in data.take(count) {
+        length = (length << 8) | *byte as usize;
+    }
+    Ok(length)
+}
+
+/// Reads the length of a constructed value and checks that its contents are all there,
+/// so a truncated packet fails here rather than somewhere inside it.
+pub(crate) fn read_contents_length(data: &mut slice::Iter<u8>) -> Result<usize, SnmpError> {
+    let before = data.len();
+    let length = read_length(data)?;
     if data.len() < length {
-        return Err(SnmpError::PacketTooShort);
+        return Err(SnmpError::PacketTooShort { offset: before - data.len(), expected: length, actual: data.len() });
+    }
+    Ok(length)
+}
+
+/// Reads the next byte, such as the tag of a value.
+pub(crate) fn next_byte(data: &mut slice::Iter<u8>) -> Result<u8, SnmpError> {
+    data.next().cloned().ok_or(SnmpError::PacketTooShort { offset: 0, expected: 1, actual: 0 })
+}
+
+/// Reads an octet string as raw bytes, without decoding it as UTF-8.
+pub(crate) fn extract_octets(data: &mut slice::Iter<u8>) -> Result<Vec<u8>, SnmpError> {
+    if next_byte(data)? != 0x04 {
+        return Err(SnmpError::InvalidType);
+    }
+
+    let length = read_contents_length(data).map_err(|e| shift_offset(e, 1))?;
+    Ok(data.take(length).cloned().collect())
+}
+
+/// Moves the offset of a `PacketTooShort` error along, for a value read after `by` other bytes.
+fn shift_offset(error: SnmpError, by: usize) -> SnmpError {
+    match error {
+        SnmpError::PacketTooShort { offset, expected, actual } =>
+            SnmpError::PacketTooShort { offset: offset + by, expected: expected, actual: actual },
+        other => other,
+    }
+}
+
+/// How many constructed values every message wraps its variables in: the message, the scoped PDU
+/// of SNMPv3, the PDU, the list of variable bindings and the variable binding itself.
+const ENVELOPE_DEPTH: usize = 5;
+
+/// Finds the first value whose length runs past the end of the data holding it, looking inside
+/// sequences, PDUs and other constructed values no more than `max_depth` deep.
+/// Only the first value is looked at when `first_only` is set, since some agents pad their
+/// datagrams after the message.
+fn find_truncation(data: &[u8], base: usize, first_only: bool, max_depth: usize) -> Option<SnmpError> {
+    let mut offset = 0;
+    while offset < data.len() {
+        let rest = &data[offset..];
+        let (tag, header, length) = match read_header(rest) {
+            Ok(header) => header,
+            Err(error @ SnmpError::PacketTooShort { .. }) => return Some(shift_offset(error, base + offset)),
+            Err(_) => return None,
+        };
+        if tag & 0x20 != 0 {
+            if max_depth == 0 {
+                return Some(SnmpError::LimitExceeded);
+            }
+            let contents = &rest[header..header + length];
+            let truncation = find_truncation(contents, base + offset + header, false, max_depth - 1);
+            if truncation.is_some() {
+                return truncation;
+            }
+        }
+        offset += header + length;
+        if first_only {
+            break;
+        }
+    }
+    None
+}
+
+/// Replaces a `PacketTooShort` error from decoding the packet with one that tells which value
+/// was cut short, counting its offset from the start of the packet. If no value was cut short,
+/// the packet is complete but lacks a value the decoder expected, so it is malformed instead.
+pub(crate) fn locate_truncation<T>(packet: &[u8], result: Result<T, SnmpError>,
+                                   limits: &DecodeLimits) -> Result<T, SnmpError> {
+    match result {
+        Err(SnmpError::PacketTooShort { .. }) => {
+            let max_depth = limits.max_depth.saturating_add(ENVELOPE_DEPTH);
+            Err(find_truncation(packet, 0, true, max_depth).unwrap_or(SnmpError::ParsingError))
+        },
+        other => other,
     }
-    
-    let ndata: Vec<_> = data.take(length).map(|i| *i).collect();
+}
+
+/// The most variable bindings a PDU may hold by default. A full UDP datagram has room for
+/// several thousand minimal ones, more than any agent sends for a legitimate request.
+pub const DEFAULT_MAX_VARBINDS: usize = 2048;
+
+/// How deeply sequences may nest inside a value by default.
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+
+/// Limits on what the parser accepts from an agent, past which decoding fails with
+/// `SnmpError::LimitExceeded` rather than doing as much work as the packet asks for.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::snmpv1::Pdu;
+/// use rust_snmp::types::{DecodeLimits, PduType, SnmpError, SnmpType};
+///
+/// let varbinds = (0..3).map(|i| (vec![1, 3, 6, 1, i].into(), SnmpType::SnmpNull)).collect();
+/// let bytes = Pdu::new(PduType::Response, 1, varbinds).encode();
+/// assert_eq!(Pdu::decode(&bytes).unwrap().varbinds.len(), 3);
+///
+/// let limits = DecodeLimits { max_varbinds: 2, ..DecodeLimits::default() };
+/// match Pdu::decode_with_limits(&bytes, &limits) {
+///     Err(SnmpError::LimitExceeded) => (),
+///     other => panic!("expected the limit to be exceeded, got {:?}", other),
+/// }
+///
+/// // Sequences nested too deeply are refused too, however short the packet.
+/// let mut nested = vec![0x05, 0x00];
+/// for _ in 0..20 {
+///     nested = [&[0x30, nested.len() as u8][..], &nested].concat();
+/// }
+/// match rust_snmp::types::read_tlv(&nested) {
+///     Err(SnmpError::LimitExceeded) => (),
+///     other => panic!("expected the limit to be exceeded, got {:?}", other),
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecodeLimits {
+    /// The most variable bindings a PDU may hold. Defaults to `DEFAULT_MAX_VARBINDS`.
+    pub max_varbinds: usize,
+    /// How deeply sequences may nest inside a value. Defaults to `DEFAULT_MAX_DEPTH`.
+    pub max_depth: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> DecodeLimits {
+        DecodeLimits {
+            max_varbinds: DEFAULT_MAX_VARBINDS,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+/// Reads a single BER-encoded value of any of the types in `SnmpType`, advancing the iterator past it.
+/// The number of bytes consumed is the difference in `len()` of the iterator before and after.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::types::{extract_value, SnmpType};
+/// let data = [0x02, 0x01, 0x2A, 0x04, 0x02, b'h', b'i'];
+/// let mut iterator = data.iter();
+/// assert_eq!(extract_value(&mut iterator).unwrap().as_integer(), Some(42));
+/// assert_eq!(extract_value(&mut iterator).unwrap().as_str(), Some("hi"));
+/// assert!(iterator.as_slice().is_empty());
+/// ```
+///
+/// Integers are two's complement and sign-extended, while the application types
+/// Counter32, Gauge32, TimeTicks and Counter64 are always unsigned.
+/// ```
+/// use rust_snmp::types::read_tlv;
+/// assert_eq!(read_tlv(&[0x02, 0x01, 0x00]).unwrap().0.as_integer(), Some(0));
+/// assert_eq!(read_tlv(&[0x02, 0x01, 0x7F]).unwrap().0.as_integer(), Some(127));
+/// assert_eq!(read_tlv(&[0x02, 0x01, 0x80]).unwrap().0.as_integer(), Some(-128));
+/// assert_eq!(read_tlv(&[0x02, 0x02, 0xFF, 0xFF]).unwrap().0.as_integer(), Some(-1));
+/// assert_eq!(read_tlv(&[0x02, 0x02, 0x00, 0xFF]).unwrap().0.as_integer(), Some(255));
+/// assert_eq!(read_tlv(&[0x02, 0x02, 0xFE, 0x0C]).unwrap().0.as_integer(), Some(-500));
+/// assert_eq!(read_tlv(&[0x02, 0x04, 0x80, 0x00, 0x00, 0x00]).unwrap().0.as_integer(), Some(-2147483648));
+/// assert_eq!(read_tlv(&[0x42, 0x02, 0xFF, 0xFF]).unwrap().0.as_gauge(), Some(65535));
+/// assert_eq!(read_tlv(&[0x41, 0x04, 0xFF, 0xFF, 0xFF, 0xFF]).unwrap().0.as_counter(), Some(4294967295));
+/// assert_eq!(read_tlv(&[0x43, 0x05, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]).unwrap().0.as_timeticks(), Some(4294967295));
+/// assert_eq!(read_tlv(&[0x46, 0x01, 0x80]).unwrap().0.as_counter64(), Some(128));
+/// ```
+pub fn extract_value(data: &mut slice::Iter<u8>) -> Result<SnmpType, SnmpError> {
+    extract_value_within(data, DEFAULT_MAX_DEPTH)
+}
+
+/// Reads a value like `extract_value`, refusing sequences nested more than `max_depth` deep.
+pub(crate) fn extract_value_within(data: &mut slice::Iter<u8>, max_depth: usize) -> Result<SnmpType, SnmpError> {
+    let (datatype, header, length) = read_header(data.as_slice())?;
+    let ndata = data.as_slice()[header..header + length].to_vec();
+    *data = data.as_slice()[header + length..].iter();
+
     let datatype = match datatype {
         0x02 => SnmpType::SnmpInteger(i64::decode_snmp(&ndata)?),
-        0x04 => SnmpType::SnmpString(String::decode_snmp(&ndata)?),
+        0x04 => match String::decode_snmp(&ndata) {
+            Ok(s) => SnmpType::SnmpString(s),
+            Err(_) => SnmpType::SnmpOctetString(ndata),
+        },
         0x05 => SnmpType::SnmpNull,
-        0x06 => SnmpType::SnmpObjectID(ndata),
+        0x06 => SnmpType::SnmpObjectID(Oid::decode_snmp(&ndata)?),
+        0x40 => SnmpType::SnmpIpAddress(Ipv4Addr::decode_snmp(&ndata)?),
+        0x41 => SnmpType::SnmpCounter32(u32::decode_snmp(&ndata)?),
+        0x42 => SnmpType::SnmpGauge32(u32::decode_snmp(&ndata)?),
+        0x43 => SnmpType::SnmpTimeTicks(u32::decode_snmp(&ndata)?),
+        0x44 => SnmpType::SnmpOpaque(ndata),
+        0x46 => SnmpType::SnmpCounter64(u64::decode_snmp(&ndata)?),
+        0x80 => SnmpType::NoSuchObject,
+        0x81 => SnmpType::NoSuchInstance,
+        0x82 => SnmpType::EndOfMibView,
+        0x30 => {
+            if max_depth == 0 {
+                return Err(SnmpError::LimitExceeded);
+            }
+            let mut contents = ndata.iter();
+            let mut values = Vec::new();
+            while contents.len() > 0 {
+                let start = header + ndata.len() - contents.len();
+                values.push(extract_value_within(&mut contents, max_depth - 1).map_err(|e| shift_offset(e, start))?);
+            }
+            SnmpType::SnmpSequence(values)
+        },
         _ => return Err(SnmpError::InvalidType),
     };
 
-    println!("Data: {:?}", datatype);
     Ok(datatype)
 }
+
+/// Reads the tag and length at the start of the slice without decoding the contents, returning
+/// the tag, the length of the header and the length of the contents. The whole value takes up
+/// the two lengths together, so this finds the boundaries of values of any type, including
+/// sequences and types `extract_value` does not know.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::types::read_header;
+/// // A varbind sequence holding an OID and a Null, followed by an unknown type.
+/// let data = [0x30, 0x06, 0x06, 0x02, 0x2B, 0x06, 0x05, 0x00, 0x9F, 0x01, 0xAA];
+/// let (tag, header, length) = read_header(&data).unwrap();
+/// assert_eq!((tag, header, length), (0x30, 2, 6));
+/// assert_eq!(read_header(&data[header..]).unwrap(), (0x06, 2, 2));
+/// assert_eq!(read_header(&data[header + length..]).unwrap(), (0x9F, 2, 1));
+/// assert!(read_header(&data[..5]).is_err());
+/// ```
+pub fn read_header(data: &[u8]) -> Result<(u8, usize, usize), SnmpError> {
+    let mut iterator = data.iter();
+    let tag = next_byte(&mut iterator)?;
+    let length = read_contents_length(&mut iterator).map_err(|e| shift_offset(e, 1))?;
+    Ok((tag, data.len() - iterator.len(), length))
+}
+
+/// Reads a single BER-encoded value from the start of the slice,
+/// returning it along with the number of bytes it took up.
+///
+/// #Examples
+/// ```
+/// use rust_snmp::types::read_tlv;
+/// let data = [0x43, 0x02, 0x01, 0x00, 0x05, 0x00];
+/// let (value, consumed) = read_tlv(&data).unwrap();
+/// assert_eq!(value.as_timeticks(), Some(256));
+/// assert_eq!(consumed, 4);
+/// assert!(read_tlv(&data[consumed..]).unwrap().0.is_null());
+/// ```
+///
+/// Every type decodes into the same value it was encoded from.
+/// ```
+/// use std::net::Ipv4Addr;
+/// use rust_snmp::types::{read_tlv, SnmpType};
+///
+/// let values = vec![SnmpType::SnmpInteger(-129),
+///                   SnmpType::SnmpInteger(i64::MAX),
+///                   SnmpType::SnmpString("echo".to_owned()),
+///                   SnmpType::SnmpOctetString(vec![0x00, 0x1b, 0xff, 0xfe, 0x80, 0x01]),
+///                   SnmpType::SnmpNull,
+///                   SnmpType::SnmpObjectID(vec![1, 3, 6, 1, 4, 1, 2021, 4294967295].into()),
+///                   SnmpType::SnmpObjectID(vec![0, 0, 8571].into()),
+///                   SnmpType::SnmpObjectID(vec![2, 999, 3].into()),
+///                   SnmpType::SnmpIpAddress(Ipv4Addr::new(192, 168, 0, 1)),
+///                   SnmpType::SnmpCounter32(u32::MAX),
+///                   SnmpType::SnmpGauge32(128),
+///                   SnmpType::SnmpTimeTicks(0),
+///                   SnmpType::SnmpOpaque(vec![0x9F, 0x78, 0x04, 0x41, 0xBC, 0x00, 0x00]),
+///                   SnmpType::SnmpCounter64(u64::MAX),
+///                   SnmpType::NoSuchInstance,
+///                   SnmpType::SnmpSequence(vec![SnmpType::SnmpInteger(1), SnmpType::SnmpNull])];
+/// for value in values {
+///     let encoded = value.encode();
+///     let (decoded, consumed) = read_tlv(&encoded).unwrap();
+///     assert_eq!(consumed, encoded.len());
+///     assert_eq!(decoded, value);
+/// }
+/// ```
+pub fn read_tlv(data: &[u8]) -> Result<(SnmpType, usize), SnmpError> {
+    let mut iterator = data.iter();
+    let value = locate_truncation(data, extract_value(&mut iterator), &DecodeLimits::default())?;
+    Ok((value, data.len() - iterator.len()))
+}